@@ -275,7 +275,7 @@ fn bench_ecommerce_errors(c: &mut Criterion) {
     });
     group.bench_function("cosine_pos_no_normalizer_2k_slice", |b| {
         let config: Config<&str> =
-            Config::token_cosine_positional(&small_slice.to_vec(), Threshold::default());
+            Config::token_cosine_positional(small_slice, Threshold::default());
         b.iter(|| group_similar::group_similar(black_box(small_slice), &config))
     });
 
@@ -320,7 +320,7 @@ fn bench_mixed(c: &mut Criterion) {
     });
     group.bench_function("cosine_pos_no_normalizer_5k_slice", |b| {
         let config: Config<&str> =
-            Config::token_cosine_positional(&small_slice.to_vec(), Threshold::default());
+            Config::token_cosine_positional(small_slice, Threshold::default());
         b.iter(|| group_similar::group_similar(black_box(small_slice), &config))
     });
 
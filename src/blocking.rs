@@ -0,0 +1,881 @@
+//! Candidate-pair generators for [`crate::cluster_with_candidates`].
+//!
+//! Each strategy maps a slice of strings to a list of `(i, j)` index pairs
+//! with `i < j`. Pairs that never appear are assumed to be above threshold —
+//! so a strategy trades recall for the work it saves. [`Blocking`] selects
+//! one of these for the string pipeline.
+
+use crate::{Blocking, Threshold};
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+impl Blocking {
+    /// Candidate pairs over `items` for this strategy, or `None` for
+    /// [`Blocking::Dense`] (every pair is a candidate).
+    pub(crate) fn candidates<S: AsRef<str> + Sync>(
+        &self,
+        items: &[S],
+    ) -> Option<Vec<(usize, usize)>> {
+        self.candidates_across(items, None)
+    }
+
+    /// Like [`Blocking::candidates`]; with `Some(split)`, only pairs
+    /// `(i, j)` with `i < split <= j` — across the two halves of `items` —
+    /// are generated, and neighborhoods and blocks are filled from the
+    /// other half alone.
+    pub(crate) fn candidates_across<S: AsRef<str> + Sync>(
+        &self,
+        items: &[S],
+        split: Option<usize>,
+    ) -> Option<Vec<(usize, usize)>> {
+        match self {
+            Blocking::Dense => None,
+            Blocking::QGram { tau, options } => Some(qgram_pairs(items, *tau, options, split)),
+            Blocking::Token { max_df } => {
+                Some(pairs_from_blocks(token_blocks(items, *max_df), split))
+            }
+            Blocking::SortedNeighborhood { window } => {
+                Some(sorted_neighborhood_pairs(items, *window, split))
+            }
+            Blocking::Prefix { tokens } => Some(pairs_from_blocks(
+                key_blocks(items, prefix_key(*tokens)),
+                split,
+            )),
+            Blocking::MinHash {
+                bands,
+                rows,
+                shingle,
+            } => Some(pairs_from_blocks(
+                minhash_blocks(items, *bands, *rows, *shingle),
+                split,
+            )),
+            Blocking::Key(key) => Some(pairs_from_blocks(key_blocks(items, |s| (key.0)(s)), split)),
+        }
+    }
+}
+
+/// User-supplied blocking key for [`Blocking::Key`]: records whose keys are
+/// equal become candidate pairs.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct BlockingKey(Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl BlockingKey {
+    /// Wrap a key function.
+    pub fn new<F>(key: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Self(Arc::new(key))
+    }
+}
+
+impl std::fmt::Debug for BlockingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BlockingKey(..)")
+    }
+}
+
+/// Gram extraction and filter settings for [`Blocking::QGram`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QGramOptions {
+    /// Gram length in characters. Clamped to `2..=5`.
+    pub q: usize,
+    /// Pad each string with `q - 1` begin and end markers, so that short
+    /// strings still produce grams and a string's first and last characters
+    /// count as much as its middle ones.
+    pub pad: bool,
+    /// Lowercase before extracting grams.
+    pub case_fold: bool,
+    /// Records with fewer distinct grams than this bypass the heuristic
+    /// count filter and pair with everything — short strings don't carry
+    /// enough signal for a proportional threshold to be reliable. Set it to
+    /// `0` for short-string data, where that would approach all-pairs:
+    /// short records then need one gram in common, and `pad` keeps most
+    /// transposed ones together.
+    pub min_grams: u32,
+    /// Grams present in more than this fraction of records are skipped by
+    /// the heuristic filter: they don't discriminate and dominate scan cost.
+    pub common_cutoff: f64,
+}
+
+impl Default for QGramOptions {
+    fn default() -> Self {
+        Self {
+            q: 3,
+            pad: false,
+            case_fold: false,
+            min_grams: 15,
+            common_cutoff: 0.8,
+        }
+    }
+}
+
+/// Generate candidate pairs for [`crate::cluster_with_candidates`] using a character
+/// trigram inverted index.
+///
+/// A pair `(i, j)` is emitted when records `i` and `j` share at least
+/// `max(1, tau_coef × min(unique_trigrams_i, unique_trigrams_j))` trigrams.
+/// Records with fewer than 15 unique trigrams bypass the filter (they don't
+/// have enough signal for the heuristic to be reliable). Very common trigrams
+/// (present in ≥80% of records) are skipped — they don't discriminate and
+/// dominate scan cost. Both limits are configurable through
+/// [`qgram_candidates_with_options`].
+///
+/// `tau_coef` controls aggressiveness: higher values reject more pairs but
+/// risk false negatives (within-threshold pairs whose trigram overlap was
+/// below `tau`). Typical range: 0.1–0.4. `cluster_with_candidates` recovers
+/// most false negatives within a component, but pairs that should bridge two
+/// components and don't appear here will fragment the cluster.
+pub fn qgram_candidates<S: AsRef<str> + Sync>(items: &[S], tau_coef: f64) -> Vec<(usize, usize)> {
+    qgram_candidates_with_options(items, tau_coef, &QGramOptions::default())
+}
+
+/// Like [`qgram_candidates`], with configurable gram length, padding, case
+/// folding, short-record minimum and common-gram cutoff.
+pub fn qgram_candidates_with_options<S: AsRef<str> + Sync>(
+    items: &[S],
+    tau_coef: f64,
+    options: &QGramOptions,
+) -> Vec<(usize, usize)> {
+    qgram_pairs(items, tau_coef, options, None)
+}
+
+fn qgram_pairs<S: AsRef<str> + Sync>(
+    items: &[S],
+    tau_coef: f64,
+    options: &QGramOptions,
+    split: Option<usize>,
+) -> Vec<(usize, usize)> {
+    let grams: Vec<Vec<u64>> = items
+        .par_iter()
+        .map(|s| unique_qgrams(s.as_ref(), options))
+        .collect();
+    let index = build_qgram_index(&grams);
+    let bound = |qi: u32, qj: u32, _: usize, _: usize| {
+        if qi < options.min_grams || qj < options.min_grams {
+            0
+        } else {
+            (tau_coef * qi.min(qj) as f64).max(1.0) as u32
+        }
+    };
+    let common = (grams.len() as f64 * options.common_cutoff) as usize;
+    generate_candidates(&grams, &index, Some(common), bound, split)
+}
+
+/// Lossless q-gram candidates for normalized edit distance
+/// ([`crate::Config::levenshtein`]) at `threshold`.
+///
+/// Two strings within `k` edits share at least `max(|G_a|, |G_b|) - k × q`
+/// distinct grams, since each edit touches at most `q` gram positions. With
+/// `k` the most edits a pair of length `max(len_a, len_b)` can be apart and
+/// still pass `threshold` — computed in the distance's own `f32` arithmetic
+/// — that bound is exact, so no pair within threshold is ever dropped: unlike [`qgram_candidates`] there is no
+/// `tau`, and `min_grams`, `common_cutoff` and `case_fold` are ignored.
+/// Pruning is strongest for long strings and strict thresholds; when the
+/// bound falls to zero every pair is a candidate.
+pub fn qgram_candidates_for_edit_distance<S: AsRef<str> + Sync>(
+    items: &[S],
+    threshold: &Threshold,
+    options: &QGramOptions,
+) -> Vec<(usize, usize)> {
+    edit_distance_pairs(items, threshold, options, None)
+}
+
+/// [`qgram_candidates_for_edit_distance`], optionally across `split` as in
+/// [`Blocking::candidates_across`].
+pub(crate) fn edit_distance_pairs<S: AsRef<str> + Sync>(
+    items: &[S],
+    threshold: &Threshold,
+    options: &QGramOptions,
+    split: Option<usize>,
+) -> Vec<(usize, usize)> {
+    let options = QGramOptions {
+        case_fold: false,
+        ..options.clone()
+    };
+    let q = options.q.clamp(2, 5) as i64;
+    let lens: Vec<usize> = items.iter().map(|s| s.as_ref().chars().count()).collect();
+    let grams: Vec<Vec<u64>> = items
+        .par_iter()
+        .map(|s| unique_qgrams(s.as_ref(), &options))
+        .collect();
+    let index = build_qgram_index(&grams);
+    let bound = |qi: u32, qj: u32, i: usize, j: usize| {
+        let max_edits = max_edits(threshold, lens[i].max(lens[j])) as i64;
+        (qi.max(qj) as i64 - max_edits * q).max(0) as u32
+    };
+    generate_candidates(&grams, &index, None, bound, split)
+}
+
+/// The largest `k` with `k / len` within `threshold`, as
+/// [`crate::Config::levenshtein`] scores it: `threshold × len` rounded in
+/// `f64` can land one short of an edit count whose `f32` distance passes.
+fn max_edits(threshold: &Threshold, len: usize) -> usize {
+    let within = |k: usize| threshold.within(k as f32 / len as f32);
+    if len == 0 {
+        return 0;
+    }
+    let mut k = ((threshold.value() * len as f64).floor() as usize).min(len);
+    while k < len && within(k + 1) {
+        k += 1;
+    }
+    while k > 0 && !within(k) {
+        k -= 1;
+    }
+    k
+}
+
+/// Generate candidate pairs from shared rare tokens.
+///
+/// Records are tokenized the same way as the cosine metrics (alphanumeric
+/// runs). A token is rare when it appears in at most `max(2, max_df × n)`
+/// records; every pair of records sharing a rare token is a candidate. Common
+/// tokens are ignored entirely — they say little about identity and their
+/// blocks would approach all-pairs.
+///
+/// Good fit for entity-resolution data where matching records share a
+/// distinctive word or code. Records with no rare tokens pair with nothing.
+pub fn token_candidates<S: AsRef<str>>(items: &[S], max_df: f64) -> Vec<(usize, usize)> {
+    pairs_from_blocks(token_blocks(items, max_df), None)
+}
+
+fn token_blocks<S: AsRef<str>>(items: &[S], max_df: f64) -> Vec<Vec<usize>> {
+    let n = items.len();
+    let (vocab, df) = crate::tokens::document_frequencies(items);
+    let max_count = ((max_df * n as f64) as u32).max(2);
+
+    let mut blocks: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        for tok in crate::tokens::tokenize(item.as_ref()) {
+            let id = vocab[tok];
+            if df[id as usize] < 2 || df[id as usize] > max_count {
+                continue;
+            }
+            let block = blocks.entry(id).or_default();
+            if block.last() != Some(&i) {
+                block.push(i);
+            }
+        }
+    }
+    blocks.into_values().collect()
+}
+
+/// Generate candidate pairs with the sorted-neighborhood method.
+///
+/// Sorts records by their string form and pairs each record with the next
+/// `window - 1` records in sorted order. Cost is O(n log n + n × window)
+/// regardless of how similar the data is, but only catches matches that sort
+/// near each other — i.e. those agreeing on a leading prefix.
+pub fn sorted_neighborhood_candidates<S: AsRef<str>>(
+    items: &[S],
+    window: usize,
+) -> Vec<(usize, usize)> {
+    sorted_neighborhood_pairs(items, window, None)
+}
+
+/// Sorted-neighborhood pairs. Across a `split`, each record is paired with
+/// the next `window - 1` records of the other half in sorted order, so
+/// records of its own half never crowd matches out of the window.
+fn sorted_neighborhood_pairs<S: AsRef<str>>(
+    items: &[S],
+    window: usize,
+    split: Option<usize>,
+) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| items[a].as_ref().cmp(items[b].as_ref()));
+
+    let mut pairs = Vec::new();
+    match split {
+        None => {
+            for (pos, &i) in order.iter().enumerate() {
+                for &j in order.iter().skip(pos + 1).take(window.saturating_sub(1)) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+        Some(split) => {
+            // Sorted positions of each half's records.
+            let (left, right): (Vec<usize>, Vec<usize>) =
+                (0..order.len()).partition(|&pos| order[pos] < split);
+            for (pos, &i) in order.iter().enumerate() {
+                let other = if i < split { &right } else { &left };
+                let start = other.partition_point(|&p| p <= pos);
+                for &p in other[start..].iter().take(window.saturating_sub(1)) {
+                    let j = order[p];
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Generate candidate pairs from records sharing their first `tokens` tokens.
+///
+/// The block key is the leading alphanumeric tokens joined by a space, so
+/// `"Target #1244"` and `"Target.com"` share the block `Target` with
+/// `tokens = 1`. Records with fewer than `tokens` tokens key on what they
+/// have.
+pub fn prefix_candidates<S: AsRef<str>>(items: &[S], tokens: usize) -> Vec<(usize, usize)> {
+    key_candidates(items, prefix_key(tokens))
+}
+
+fn prefix_key(tokens: usize) -> impl Fn(&str) -> String {
+    move |s| {
+        crate::tokens::tokenize(s)
+            .take(tokens)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Generate candidate pairs from records whose `key` values are equal.
+pub fn key_candidates<S, K>(items: &[S], key: K) -> Vec<(usize, usize)>
+where
+    S: AsRef<str>,
+    K: Fn(&str) -> String,
+{
+    pairs_from_blocks(key_blocks(items, key), None)
+}
+
+fn key_blocks<S, K>(items: &[S], key: K) -> Vec<Vec<usize>>
+where
+    S: AsRef<str>,
+    K: Fn(&str) -> String,
+{
+    let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        blocks.entry(key(item.as_ref())).or_default().push(i);
+    }
+    blocks.into_values().collect()
+}
+
+/// Shingle set used by [`minhash_candidates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shingle {
+    /// Overlapping character n-grams of the given length. Records shorter
+    /// than `n` characters use the whole string as their only shingle.
+    QGram(usize),
+    /// Alphanumeric tokens, as used by the cosine metrics.
+    Token,
+}
+
+/// Generate candidate pairs with MinHash locality-sensitive hashing.
+///
+/// Each record's shingle set is summarized by `bands × rows` MinHash values;
+/// the signature is cut into `bands` bands of `rows` values and records whose
+/// band agrees exactly land in the same bucket. Only records sharing a bucket
+/// become candidates, so the cost grows with `n` and bucket sizes rather than
+/// with `n²`.
+///
+/// Two records with shingle Jaccard similarity `s` become candidates with
+/// probability `1 - (1 - s^rows)^bands`. The inflection point sits near
+/// `(1 / bands)^(1 / rows)`: e.g. 20 bands × 5 rows catches most pairs above
+/// ~0.55 Jaccard. Records with no shingles (empty strings) pair with nothing.
+pub fn minhash_candidates<S: AsRef<str> + Sync>(
+    items: &[S],
+    bands: usize,
+    rows: usize,
+    shingle: Shingle,
+) -> Vec<(usize, usize)> {
+    pairs_from_blocks(minhash_blocks(items, bands, rows, shingle), None)
+}
+
+fn minhash_blocks<S: AsRef<str> + Sync>(
+    items: &[S],
+    bands: usize,
+    rows: usize,
+    shingle: Shingle,
+) -> Vec<Vec<usize>> {
+    let hashes = bands * rows;
+    let signatures: Vec<Option<Vec<u64>>> = items
+        .par_iter()
+        .map(|item| minhash_signature(&shingle_hashes(item.as_ref(), shingle), hashes))
+        .collect();
+
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (i, signature) in signatures.iter().enumerate() {
+        if let Some(signature) = signature {
+            for (band, values) in signature.chunks(rows.max(1)).enumerate() {
+                let key = values.iter().fold(band as u64, |acc, &v| mix64(acc ^ v));
+                buckets.entry((band, key)).or_default().push(i);
+            }
+        }
+    }
+    buckets.into_values().collect()
+}
+
+/// 64-bit hashes of each distinct shingle in `s`.
+fn shingle_hashes(s: &str, shingle: Shingle) -> Vec<u64> {
+    let mut hashes: Vec<u64> = match shingle {
+        Shingle::QGram(n) => {
+            let chars: Vec<char> = s.chars().collect();
+            if chars.is_empty() {
+                Vec::new()
+            } else if chars.len() <= n {
+                vec![hash_chars(&chars)]
+            } else {
+                chars.windows(n.max(1)).map(hash_chars).collect()
+            }
+        }
+        Shingle::Token => crate::tokens::tokenize(s)
+            .map(|t| hash_bytes(t.as_bytes()))
+            .collect(),
+    };
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
+
+/// Minimum of each of `count` seeded hash functions over `shingles`, or
+/// `None` for an empty set.
+fn minhash_signature(shingles: &[u64], count: usize) -> Option<Vec<u64>> {
+    if shingles.is_empty() {
+        return None;
+    }
+    Some(
+        (0..count as u64)
+            .map(|seed| {
+                let seed = mix64(seed.wrapping_add(0x9e37_79b9_7f4a_7c15));
+                shingles.iter().map(|&h| mix64(h ^ seed)).min().unwrap()
+            })
+            .collect(),
+    )
+}
+
+/// FNV-1a over raw bytes — stable across runs and platforms, unlike
+/// `std`'s randomly-seeded hasher.
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |acc, &b| {
+        (acc ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn hash_chars(chars: &[char]) -> u64 {
+    chars.iter().fold(0xcbf2_9ce4_8422_2325, |acc, &c| {
+        (acc ^ c as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// SplitMix64 finalizer: a cheap, well-distributed 64-bit mixing function.
+fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Every within-block pair — with `Some(split)`, only those straddling
+/// `split` — deduplicated across blocks and sorted. Block members must be in
+/// ascending index order.
+fn pairs_from_blocks(blocks: Vec<Vec<usize>>, split: Option<usize>) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for block in blocks {
+        match split {
+            None => {
+                for (a, &i) in block.iter().enumerate() {
+                    for &j in &block[a + 1..] {
+                        pairs.push((i, j));
+                    }
+                }
+            }
+            Some(split) => {
+                let (left, right) = block.split_at(block.partition_point(|&i| i < split));
+                for &i in left {
+                    pairs.extend(right.iter().map(|&j| (i, j)));
+                }
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+/// Distinct grams of `s`, each packed into a `u64` key. A non-empty string
+/// shorter than `q` (after padding) is its own single gram.
+///
+/// A gram of up to nine ASCII characters is packed losslessly, seven bits per
+/// character; any other gram is hashed, with the top bit set so the two
+/// encodings never collide. Keys depend only on the gram's characters, so
+/// ASCII and non-ASCII strings sharing a gram agree on its key.
+fn unique_qgrams(s: &str, options: &QGramOptions) -> Vec<u64> {
+    let q = options.q.clamp(2, 5);
+    let mut grams: Vec<u64> = if s.is_ascii() && !options.pad {
+        // Fast path: slide over bytes directly, no char buffer.
+        let bytes = s.as_bytes();
+        bytes
+            .windows(q)
+            .map(|w| {
+                w.iter().fold(0u64, |acc, &b| {
+                    let b = if options.case_fold {
+                        b.to_ascii_lowercase()
+                    } else {
+                        b
+                    };
+                    (acc << 7) | b as u64
+                })
+            })
+            .collect()
+    } else {
+        let mut chars: Vec<char> = Vec::with_capacity(s.len() + 2 * (q - 1));
+        if options.pad {
+            chars.extend(std::iter::repeat_n(PAD_START, q - 1));
+        }
+        if options.case_fold {
+            chars.extend(s.chars().flat_map(char::to_lowercase));
+        } else {
+            chars.extend(s.chars());
+        }
+        if options.pad {
+            chars.extend(std::iter::repeat_n(PAD_END, q - 1));
+        }
+        chars.windows(q).map(pack_gram).collect()
+    };
+    if grams.is_empty() && !s.is_empty() {
+        let chars: Vec<char> = if options.case_fold {
+            s.chars().flat_map(char::to_lowercase).collect()
+        } else {
+            s.chars().collect()
+        };
+        grams.push(pack_gram(&chars));
+    }
+    grams.sort_unstable();
+    grams.dedup();
+    grams
+}
+
+const PAD_START: char = '\u{2}';
+const PAD_END: char = '\u{3}';
+
+fn pack_gram(chars: &[char]) -> u64 {
+    if chars.len() <= 9 && chars.iter().all(char::is_ascii) {
+        chars.iter().fold(0u64, |acc, &c| (acc << 7) | c as u64)
+    } else {
+        hash_chars(chars) | (1 << 63)
+    }
+}
+
+fn build_qgram_index(grams: &[Vec<u64>]) -> HashMap<u64, Vec<usize>> {
+    let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, record) in grams.iter().enumerate() {
+        for &g in record {
+            index.entry(g).or_default().push(i);
+        }
+    }
+    index
+}
+
+/// Incrementally-built trigram index for looking up records similar to a
+/// probe string, one probe at a time. Ids are assigned in insertion order.
+#[derive(Default)]
+pub(crate) struct QGramIndex {
+    options: QGramOptions,
+    postings: HashMap<u64, Vec<usize>>,
+    qgram_counts: Vec<u32>,
+    /// Records with too few trigrams for the count filter; always candidates.
+    short: Vec<usize>,
+}
+
+impl QGramIndex {
+    pub(crate) fn with_options(options: QGramOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn insert(&mut self, s: &str) -> usize {
+        let id = self.qgram_counts.len();
+        let grams = unique_qgrams(s, &self.options);
+        for &g in &grams {
+            self.postings.entry(g).or_default().push(id);
+        }
+        if (grams.len() as u32) < self.options.min_grams {
+            self.short.push(id);
+        }
+        self.qgram_counts.push(grams.len() as u32);
+        id
+    }
+
+    /// Ids of indexed records passing the count filter against `s`, ascending.
+    pub(crate) fn candidates(&self, s: &str, tau_coef: f64) -> Vec<usize> {
+        let grams = unique_qgrams(s, &self.options);
+        let q_count = grams.len() as u32;
+        if q_count < self.options.min_grams {
+            return (0..self.qgram_counts.len()).collect();
+        }
+
+        let mut counts: HashMap<usize, u32> = HashMap::new();
+        for g in &grams {
+            if let Some(bucket) = self.postings.get(g) {
+                for &id in bucket {
+                    *counts.entry(id).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut result: Vec<usize> = counts
+            .into_iter()
+            .filter(|&(id, shared)| {
+                let min_q = q_count.min(self.qgram_counts[id]) as f64;
+                shared >= (tau_coef * min_q).max(1.0) as u32
+            })
+            .map(|(id, _)| id)
+            .collect();
+        result.extend(self.short.iter().copied());
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+}
+
+/// Scan every `(i, j > i)` pair — or with `Some(split)` every pair with
+/// `i < split <= j` — and emit those sharing at least
+/// `bound(|G_i|, |G_j|, i, j)` grams. Buckets larger than `common` are not
+/// counted; pass `None` to count every gram.
+fn generate_candidates<B>(
+    grams: &[Vec<u64>],
+    index: &HashMap<u64, Vec<usize>>,
+    common: Option<usize>,
+    bound: B,
+    split: Option<usize>,
+) -> Vec<(usize, usize)>
+where
+    B: Fn(u32, u32, usize, usize) -> u32 + Sync,
+{
+    let n = grams.len();
+    let common = common.unwrap_or(usize::MAX);
+    let rows = split.unwrap_or(n).min(n);
+
+    (0..rows)
+        .into_par_iter()
+        .flat_map(|i| {
+            let mut counts = vec![0u32; n];
+
+            for g in &grams[i] {
+                if let Some(bucket) = index.get(g) {
+                    if bucket.len() > common {
+                        continue;
+                    }
+                    for &j in bucket {
+                        if j > i {
+                            counts[j] += 1;
+                        }
+                    }
+                }
+            }
+
+            let qi_count = grams[i].len() as u32;
+            ((i + 1).max(split.unwrap_or(0))..n)
+                .filter(|&j| counts[j] >= bound(qi_count, grams[j].len() as u32, i, j))
+                .map(|j| (i, j))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn token_candidates_pair_records_sharing_rare_tokens() {
+        let items = vec![
+            "acme widget order",
+            "acme gadget order",
+            "zenith widget order",
+            "zenith tool order",
+            "unrelated thing order",
+        ];
+        // "order" appears everywhere and is ignored; "widget", "acme" and
+        // "zenith" each appear twice.
+        let pairs = token_candidates(&items, 0.5);
+        assert_eq!(pairs, vec![(0, 1), (0, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn sorted_neighborhood_pairs_within_window() {
+        let items = vec!["delta", "alpha", "charlie", "bravo"];
+        // Sorted: alpha(1), bravo(3), charlie(2), delta(0).
+        let pairs = sorted_neighborhood_candidates(&items, 2);
+        assert_eq!(pairs, vec![(0, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn prefix_candidates_group_on_leading_tokens() {
+        let items = vec!["Target #1244", "Target.com", "Amazon.com", "Target"];
+        assert_eq!(prefix_candidates(&items, 1), vec![(0, 1), (0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn minhash_candidates_pair_near_duplicates_only() {
+        let items = vec![
+            "connection refused by upstream host alpha-01",
+            "template missing for storefront product page",
+            "connection refused by upstream host alpha-02",
+            "",
+        ];
+        let pairs = minhash_candidates(&items, 20, 3, Shingle::QGram(3));
+        assert_eq!(pairs, vec![(0, 2)]);
+    }
+
+    #[test]
+    fn minhash_candidates_are_deterministic() {
+        let items = vec!["alpha bravo", "alpha bravo charlie", "delta echo"];
+        assert_eq!(
+            minhash_candidates(&items, 8, 2, Shingle::Token),
+            minhash_candidates(&items, 8, 2, Shingle::Token)
+        );
+    }
+
+    #[test]
+    fn qgrams_are_character_based() {
+        let options = QGramOptions::default();
+        // Three characters, six bytes: one gram, not four byte windows.
+        assert_eq!(unique_qgrams("éèê", &options).len(), 1);
+        // The shared ASCII gram packs identically in both strings.
+        let ascii = unique_qgrams("abc", &options);
+        let mixed = unique_qgrams("abcé", &options);
+        assert!(mixed.contains(&ascii[0]));
+    }
+
+    #[test]
+    fn qgram_padding_and_case_folding() {
+        let options = QGramOptions {
+            q: 2,
+            pad: true,
+            case_fold: true,
+            ..QGramOptions::default()
+        };
+        // ^a, ab, b$ — and "AB" folds onto the same grams.
+        assert_eq!(unique_qgrams("ab", &options).len(), 3);
+        assert_eq!(unique_qgrams("ab", &options), unique_qgrams("AB", &options));
+        assert_eq!(unique_qgrams("a", &QGramOptions::default()).len(), 1);
+        assert!(unique_qgrams("", &QGramOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn short_records_need_a_shared_gram() {
+        let items = vec!["db down", "db down!", "disk full", "err", "err!"];
+        let filtered = QGramOptions {
+            min_grams: 0,
+            ..QGramOptions::default()
+        };
+        assert_eq!(
+            qgram_candidates_with_options(&items, 0.3, &filtered),
+            vec![(0, 1), (3, 4)]
+        );
+
+        let mut index = QGramIndex::with_options(filtered);
+        for item in &items {
+            index.insert(item);
+        }
+        assert_eq!(index.candidates("db up", 0.3), vec![0, 1]);
+
+        let bypass = QGramOptions {
+            min_grams: 3,
+            ..QGramOptions::default()
+        };
+        let pairs = qgram_candidates_with_options(&items, 0.3, &bypass);
+        assert!(pairs.contains(&(2, 3)) && !pairs.contains(&(0, 2)));
+        assert_eq!(qgram_candidates(&items, 0.3).len(), 10);
+    }
+
+    #[test]
+    fn edit_distance_candidates_keep_every_pair_within_threshold() {
+        let items = vec![
+            "connection refused by upstream host alpha",
+            "connection refused by upstream host alpah",
+            "template missing for storefront product page",
+            "connection refused by upstream host",
+            "xx",
+            "xy",
+        ];
+        let threshold: Threshold = 0.2_f64.try_into().unwrap();
+        let options = QGramOptions::default();
+        let pairs = qgram_candidates_for_edit_distance(&items, &threshold, &options);
+
+        for i in 0..items.len() {
+            for j in (i + 1)..items.len() {
+                let max_len = items[i].len().max(items[j].len());
+                let within = crate::edit_distance::levenshtein(items[i], items[j]) as f64
+                    <= 0.2 * max_len as f64;
+                if within {
+                    assert!(pairs.contains(&(i, j)), "lost ({}, {})", i, j);
+                }
+            }
+        }
+        assert!(!pairs.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn edit_distance_bound_uses_the_metric_rounding() {
+        // 0.29 × 100 is 28.999… in f64, but 29 edits in 100 characters is
+        // 0.29_f32, which passes a 0.29 threshold.
+        let threshold: Threshold = 0.29_f64.try_into().unwrap();
+        assert_eq!(max_edits(&threshold, 100), 29);
+
+        let a: String = (0..100)
+            .map(|i| char::from_u32(0x4e00 + i).unwrap())
+            .collect();
+        let b: String = a
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if i % 3 == 0 && i < 87 {
+                    char::from_u32(0x5e00 + i as u32).unwrap()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let distance = crate::edit_distance::normalized_levenshtein(&a, &b);
+        assert!(threshold.within(distance));
+        let pairs =
+            qgram_candidates_for_edit_distance(&[a, b], &threshold, &QGramOptions::default());
+        assert_eq!(pairs, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn key_blocking_dispatches_through_blocking() {
+        let items = vec!["a-1", "b-1", "a-2"];
+        let blocking = Blocking::Key(BlockingKey::new(|s| s[..1].to_string()));
+        assert_eq!(blocking.candidates(&items), Some(vec![(0, 2)]));
+        assert_eq!(Blocking::Dense.candidates(&items), None);
+    }
+
+    #[test]
+    fn candidates_across_a_split_only_straddle_it() {
+        let items = vec!["acme 1", "acme 2", "acme 3", "acme 9", "zen 1"];
+        let sorted = Blocking::SortedNeighborhood { window: 2 };
+        assert_eq!(
+            sorted.candidates(&items),
+            Some(vec![(0, 1), (1, 2), (2, 3), (3, 4)])
+        );
+        assert_eq!(
+            sorted.candidates_across(&items, Some(3)),
+            Some(vec![(0, 3), (1, 3), (2, 3)])
+        );
+
+        let prefix = Blocking::Prefix { tokens: 1 };
+        assert_eq!(
+            prefix.candidates_across(&items, Some(2)),
+            Some(vec![(0, 2), (0, 3), (1, 2), (1, 3)])
+        );
+        let qgram = Blocking::QGram {
+            tau: 0.0,
+            options: QGramOptions {
+                min_grams: 100,
+                ..QGramOptions::default()
+            },
+        };
+        assert_eq!(
+            qgram.candidates_across(&items, Some(4)),
+            Some((0..4).map(|i| (i, 4)).collect())
+        );
+    }
+}
@@ -7,29 +7,59 @@ use std::sync::Arc;
 /// Candidate-pair generation strategy used by the string pipeline.
 ///
 /// Controls whether [`crate::group_similar`] computes the full O(n²) distance
-/// matrix or builds a sparse candidate graph first. See [`crate::qgram_candidates`]
-/// for the q-gram strategy details.
-#[derive(Debug, Clone)]
+/// matrix or builds a sparse candidate graph first. Strategies run over the
+/// deduplicated records — [`Blocking::SortedNeighborhood`] sorts their
+/// normalized keys, the rest read the records themselves. See the matching
+/// `*_candidates` function for each strategy's details.
+#[derive(Debug, Clone, Default)]
 pub enum Blocking {
     /// Compute the full distance matrix. Ground-truth partition, O(n²) memory
     /// and work. Right for small inputs or when correctness matters more than
     /// throughput.
+    #[default]
     Dense,
-    /// Use a character trigram inverted index to emit candidate pairs, then
+    /// Use a character q-gram inverted index to emit candidate pairs, then
     /// cluster within connected components. Dramatically reduces work for
     /// sparse-similar data at the cost of possible cluster fragmentation
-    /// when truly-similar records share few trigrams.
+    /// when truly-similar records share few q-grams.
     QGram {
         /// Filter aggressiveness. Higher values reject more candidates;
         /// typical range: 0.1–0.4.
         tau: f64,
+        /// Gram length, padding and case folding.
+        options: crate::QGramOptions,
     },
-}
-
-impl Default for Blocking {
-    fn default() -> Self {
-        Self::Dense
-    }
+    /// Pair records sharing any rare token. See [`crate::token_candidates`].
+    Token {
+        /// Largest fraction of records a token may appear in and still count
+        /// as rare; typical range: 0.01–0.1.
+        max_df: f64,
+    },
+    /// Pair each record with its neighbors in sorted order. See
+    /// [`crate::sorted_neighborhood_candidates`].
+    SortedNeighborhood {
+        /// Number of consecutive sorted records compared together.
+        window: usize,
+    },
+    /// Pair records sharing their leading tokens. See
+    /// [`crate::prefix_candidates`].
+    Prefix {
+        /// Number of leading tokens in the block key.
+        tokens: usize,
+    },
+    /// Pair records landing in the same MinHash-LSH bucket. See
+    /// [`crate::minhash_candidates`].
+    MinHash {
+        /// Number of bands; more bands catch lower-similarity pairs.
+        bands: usize,
+        /// Hashes per band; more rows make each band stricter.
+        rows: usize,
+        /// What each record's shingle set is built from.
+        shingle: crate::Shingle,
+    },
+    /// Pair records whose user-supplied keys are equal. See
+    /// [`crate::key_candidates`].
+    Key(crate::BlockingKey),
 }
 
 /// Pre-fit cosine model carried alongside the distance closure when the
@@ -42,6 +72,23 @@ pub(super) struct CosineData {
     pub(super) positional: bool,
 }
 
+impl CosineData {
+    /// TF-IDF vector of `s` under the fitted vocabulary.
+    pub(super) fn vectorize(&self, s: &str) -> Vec<(u32, f32)> {
+        if self.positional {
+            crate::tokens::vectorize_positional(s, &self.vocab, &self.idf)
+        } else {
+            crate::tokens::vectorize(s, &self.vocab, &self.idf)
+        }
+    }
+}
+
+/// A lossless q-gram count-filter bound implied by the configured metric;
+/// see [`crate::qgram_candidates_for_edit_distance`].
+pub(super) enum GramBound {
+    NormalizedEditDistance,
+}
+
 /// Configuration for the string pipeline ([`crate::group_similar`]).
 ///
 /// Bundles a similarity metric (`compare`), a clustering threshold and
@@ -61,6 +108,12 @@ pub struct Config<V> {
     pub(super) normalize: Box<dyn Fn(&str) -> String + Send + Sync>,
     pub(super) blocking: Blocking,
     pub(super) cosine: Option<CosineData>,
+    /// Set by [`Config::stack_trace`], so the string pipeline parses each
+    /// representative's frames once instead of on every comparison.
+    pub(super) frames: Option<crate::stacktrace::FrameParser>,
+    /// Set when [`Blocking::QGram`] can use an exact bound for this metric
+    /// instead of the `tau` heuristic.
+    pub(super) gram_bound: Option<GramBound>,
 }
 
 impl<V: AsRef<str>> Config<V> {
@@ -75,9 +128,40 @@ impl<V: AsRef<str>> Config<V> {
             normalize: Box::new(crate::normalize::identity),
             blocking: Blocking::default(),
             cosine: None,
+            frames: None,
+            gram_bound: None,
+        }
+    }
+
+    /// Construct a configuration using normalized Levenshtein distance: edit
+    /// distance over characters divided by the longer string's length.
+    ///
+    /// Unlike the other metrics this one admits an exact q-gram count filter,
+    /// so [`Blocking::QGram`] ignores its `tau` and only prunes pairs that
+    /// provably exceed `threshold`.
+    pub fn levenshtein(threshold: Threshold) -> Self {
+        Config {
+            threshold,
+            method: Method::Complete,
+            compare: Box::new(|a, b| {
+                crate::Distance::clamped(crate::edit_distance::normalized_levenshtein(
+                    a.as_ref(),
+                    b.as_ref(),
+                ))
+            }),
+            normalize: Box::new(crate::normalize::identity),
+            blocking: Blocking::default(),
+            cosine: None,
+            frames: None,
+            gram_bound: Some(GramBound::NormalizedEditDistance),
         }
     }
 
+    /// Distance between two records under this config's metric.
+    pub fn distance(&self, a: &V, b: &V) -> crate::Distance {
+        (self.compare)(a, b)
+    }
+
     /// Set a normalizer that transforms strings before deduplication.
     ///
     /// Records whose normalized forms are identical are treated as duplicates,
@@ -94,14 +178,18 @@ impl<V: AsRef<str>> Config<V> {
     /// Replace the distance closure.
     ///
     /// Also clears any metric-specific precompute on this config — e.g., a
-    /// [`Config::token_cosine`] config's cached vocab/IDF — so the new
-    /// closure is the only thing [`crate::group_similar`] consults.
+    /// [`Config::token_cosine`] config's cached vocab/IDF, the parsed
+    /// frames of [`Config::stack_trace`], or the exact blocking bound of
+    /// [`Config::levenshtein`] — so the new closure is the
+    /// only thing [`crate::group_similar`] consults.
     pub fn with_compare<F>(mut self, compare: F) -> Self
     where
         F: Fn(&V, &V) -> crate::Distance + Send + Sync + 'static,
     {
         self.compare = Box::new(compare);
         self.cosine = None;
+        self.frames = None;
+        self.gram_bound = None;
         self
     }
 
@@ -112,18 +200,58 @@ impl<V: AsRef<str>> Config<V> {
     /// more pairs (faster, but more risk of fragmenting clusters whose
     /// members share few trigrams). Typical range: 0.1–0.4.
     pub fn with_blocking(mut self, tau: f64) -> Self {
-        self.blocking = Blocking::QGram { tau };
+        self.blocking = Blocking::QGram {
+            tau,
+            options: crate::QGramOptions::default(),
+        };
+        self
+    }
+
+    /// Use the given candidate-pair strategy.
+    pub fn with_blocking_strategy(mut self, blocking: Blocking) -> Self {
+        self.blocking = blocking;
         self
     }
 
     /// Force the dense pipeline (full O(n²) distance matrix). This is the
-    /// default — provided as an explicit setter so callers can override a
-    /// previously-configured [`Blocking::QGram`].
+    /// default for every constructor but [`Config::stack_trace`] — provided
+    /// as an explicit setter so callers can override a previously-configured
+    /// blocking strategy.
     pub fn without_blocking(mut self) -> Self {
         self.blocking = Blocking::Dense;
         self
     }
 
+    /// Construct a configuration that compares multi-line stack traces by
+    /// their frames rather than their messages.
+    ///
+    /// Java, Ruby and Python frames are parsed with
+    /// [`crate::parse_frames`], without line numbers, and two traces are as
+    /// close as their position-weighted frame overlap, innermost frames
+    /// counting most. Records without frames fall back to Jaro-Winkler
+    /// among themselves.
+    ///
+    /// Unlike the other constructors this one blocks by default, with a
+    /// [`Blocking::Key`] on each trace's innermost frame: traces thrown from
+    /// the same place are compared however different their messages, and
+    /// ones thrown from different places already differ by their heaviest
+    /// frame.
+    pub fn stack_trace(threshold: Threshold) -> Self {
+        let parser = crate::stacktrace::FrameParser::new();
+        let metric = crate::stacktrace::stack_trace_metric(parser.clone());
+        let key = parser.clone();
+        Config {
+            threshold,
+            method: Method::Complete,
+            compare: Box::new(move |a, b| metric(a.as_ref(), b.as_ref())),
+            normalize: Box::new(crate::normalize::identity),
+            blocking: Blocking::Key(crate::BlockingKey::new(move |s| key.innermost(s))),
+            cosine: None,
+            frames: Some(parser),
+            gram_bound: None,
+        }
+    }
+
     /// Construct a configuration that compares records using IDF-weighted
     /// token cosine similarity, fitted to the supplied corpus.
     ///
@@ -138,31 +266,7 @@ impl<V: AsRef<str>> Config<V> {
     /// rare distinctive tokens (entity identifiers, codes) dominate the score.
     pub fn token_cosine(corpus: &[V], threshold: Threshold) -> Self {
         let (vocab, idf) = crate::tokens::build_idf(corpus);
-        let vocab = Arc::new(vocab);
-        let idf = Arc::new(idf);
-
-        let compare = {
-            let vocab = Arc::clone(&vocab);
-            let idf = Arc::clone(&idf);
-            move |a: &V, b: &V| -> crate::Distance {
-                let va = crate::tokens::vectorize(a.as_ref(), &vocab, &idf);
-                let vb = crate::tokens::vectorize(b.as_ref(), &vocab, &idf);
-                crate::Distance::clamped(1.0 - crate::tokens::sparse_cosine(&va, &vb))
-            }
-        };
-
-        Config {
-            threshold,
-            method: Method::Complete,
-            compare: Box::new(compare),
-            normalize: Box::new(crate::normalize::identity),
-            blocking: Blocking::default(),
-            cosine: Some(CosineData {
-                vocab,
-                idf,
-                positional: false,
-            }),
-        }
+        Self::cosine(vocab, idf, false, threshold)
     }
 
     /// Like [`token_cosine`], but each token's contribution is scaled by
@@ -175,15 +279,31 @@ impl<V: AsRef<str>> Config<V> {
     /// overlap.
     pub fn token_cosine_positional(corpus: &[V], threshold: Threshold) -> Self {
         let (vocab, idf) = crate::tokens::build_idf(corpus);
-        let vocab = Arc::new(vocab);
-        let idf = Arc::new(idf);
+        Self::cosine(vocab, idf, true, threshold)
+    }
+
+    /// Token cosine over a fitted vocabulary and IDF table.
+    fn cosine(
+        vocab: HashMap<String, u32>,
+        idf: Vec<f32>,
+        positional: bool,
+        threshold: Threshold,
+    ) -> Self {
+        let cosine = CosineData {
+            vocab: Arc::new(vocab),
+            idf: Arc::new(idf),
+            positional,
+        };
 
         let compare = {
-            let vocab = Arc::clone(&vocab);
-            let idf = Arc::clone(&idf);
+            let cosine = CosineData {
+                vocab: Arc::clone(&cosine.vocab),
+                idf: Arc::clone(&cosine.idf),
+                positional,
+            };
             move |a: &V, b: &V| -> crate::Distance {
-                let va = crate::tokens::vectorize_positional(a.as_ref(), &vocab, &idf);
-                let vb = crate::tokens::vectorize_positional(b.as_ref(), &vocab, &idf);
+                let va = cosine.vectorize(a.as_ref());
+                let vb = cosine.vectorize(b.as_ref());
                 crate::Distance::clamped(1.0 - crate::tokens::sparse_cosine(&va, &vb))
             }
         };
@@ -194,15 +314,31 @@ impl<V: AsRef<str>> Config<V> {
             compare: Box::new(compare),
             normalize: Box::new(crate::normalize::identity),
             blocking: Blocking::default(),
-            cosine: Some(CosineData {
-                vocab,
-                idf,
-                positional: true,
-            }),
+            cosine: Some(cosine),
+            frames: None,
+            gram_bound: None,
         }
     }
 }
 
+impl<V: AsRef<str> + crate::Weighted> Config<V> {
+    /// Like [`Config::token_cosine`], with IDF fitted as if each record
+    /// occurred [`weight`](crate::Weighted::weight) times — for
+    /// pre-aggregated input, where a token on one line seen a million times
+    /// is common, not rare.
+    pub fn token_cosine_weighted(corpus: &[V], threshold: Threshold) -> Self {
+        let (vocab, idf) = crate::tokens::build_weighted_idf(corpus, |r| r.weight() as f32);
+        Self::cosine(vocab, idf, false, threshold)
+    }
+
+    /// [`Config::token_cosine_positional`] with weighted IDF; see
+    /// [`Config::token_cosine_weighted`].
+    pub fn token_cosine_positional_weighted(corpus: &[V], threshold: Threshold) -> Self {
+        let (vocab, idf) = crate::tokens::build_weighted_idf(corpus, |r| r.weight() as f32);
+        Self::cosine(vocab, idf, true, threshold)
+    }
+}
+
 /// `Threshold` is a newtype wrapper describing how permissive comparisons are for a given
 /// comparison closure.
 ///
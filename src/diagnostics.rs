@@ -0,0 +1,307 @@
+//! Measuring what a blocking strategy costs in recall.
+//!
+//! Blocking only ever compares candidate pairs, so a within-threshold pair it
+//! fails to emit is silently lost. These checks compare the candidates
+//! against the dense metric on a sample of the deduplicated records.
+
+use crate::{
+    candidate_pairs, cluster, cluster_with_candidates, deduplicate, similarity_matrix, Blocking,
+    Clusters, Config, Distance,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::{HashMap, HashSet};
+
+/// Outcome of [`estimate_recall`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecallEstimate {
+    /// Deduplicated records in the sample.
+    pub sampled: usize,
+    /// Sampled pairs within threshold under the dense metric.
+    pub true_matches: usize,
+    /// Of those, pairs the blocking strategy did not emit as candidates.
+    pub lost: usize,
+}
+
+impl RecallEstimate {
+    /// Fraction of true matches kept, in `[0, 1]`. `1.0` when the sample
+    /// holds no true matches.
+    pub fn recall(&self) -> f64 {
+        if self.true_matches == 0 {
+            1.0
+        } else {
+            (self.true_matches - self.lost) as f64 / self.true_matches as f64
+        }
+    }
+}
+
+/// Estimate how many within-threshold pairs `config`'s blocking strategy
+/// drops.
+///
+/// Runs the same normalize → dedup → candidate generation as
+/// [`crate::group_similar`], then computes every pair among up to `sample`
+/// evenly-spaced deduplicated records with the dense metric and counts the
+/// within-threshold pairs missing from the candidates. [`crate::Blocking::Dense`]
+/// always reports no losses.
+pub fn estimate_recall<V>(records: &[V], config: &Config<V>, sample: usize) -> RecallEstimate
+where
+    V: AsRef<str> + Sync,
+{
+    let deduped = deduplicate(records, &*config.normalize);
+    let candidates: Option<HashSet<(usize, usize)>> =
+        candidate_pairs(&deduped.representatives, &deduped.keys, config)
+            .map(|c| c.into_iter().collect());
+    let sampled = sample_indices(deduped.representatives.len(), sample);
+
+    let pairs: Vec<(usize, usize)> = sampled
+        .iter()
+        .enumerate()
+        .flat_map(|(a, &i)| sampled[a + 1..].iter().map(move |&j| (i, j)))
+        .collect();
+    let (true_matches, lost) = pairs
+        .into_par_iter()
+        .filter(|&(i, j)| {
+            let d = config.distance(deduped.representatives[i], deduped.representatives[j]);
+            config.threshold.within(d.value())
+        })
+        .map(|pair| match &candidates {
+            Some(c) if !c.contains(&pair) => (1, 1),
+            _ => (1, 0),
+        })
+        .reduce(|| (0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+    RecallEstimate {
+        sampled: sampled.len(),
+        true_matches,
+        lost,
+    }
+}
+
+/// Outcome of [`blocking_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockingReport {
+    /// Candidate pairs the strategy emitted over the sample.
+    pub candidate_pairs: usize,
+    /// Every pair over the sample, `n × (n - 1) / 2`.
+    pub total_pairs: usize,
+    /// Within-threshold pairs kept and lost by the strategy.
+    pub recall: RecallEstimate,
+    /// Adjusted Rand index between the dense and blocked partitions of the
+    /// sample: `1.0` when identical, around `0.0` for unrelated partitions.
+    pub adjusted_rand_index: f64,
+    /// Largest q-gram `tau` (in steps of 0.05) whose candidates reach the
+    /// target recall on the sample. `None` unless the strategy is
+    /// [`Blocking::QGram`] with a metric whose candidates depend on `tau`:
+    /// under [`Config::levenshtein`] q-gram blocking is lossless and ignores
+    /// it.
+    pub suggested_tau: Option<f64>,
+}
+
+/// Compare `config`'s blocking strategy against the dense pipeline.
+///
+/// Takes up to `sample` evenly-spaced deduplicated records, clusters them
+/// with both [`cluster`] and [`cluster_with_candidates`] (candidates from
+/// `config`'s strategy run over the sample alone), and reports how much
+/// work blocking saved, how many within-threshold pairs it lost, and how far
+/// apart the two partitions are. For [`Blocking::QGram`], also suggests the
+/// most aggressive `tau` that keeps pair recall at or above `target_recall`.
+pub fn blocking_report<V>(
+    records: &[V],
+    config: &Config<V>,
+    sample: usize,
+    target_recall: f64,
+) -> BlockingReport
+where
+    V: AsRef<str> + Sync,
+{
+    let deduped = deduplicate(records, &*config.normalize);
+    let sampled = sample_indices(deduped.representatives.len(), sample);
+    let reps: Vec<&V> = sampled
+        .iter()
+        .map(|&i| deduped.representatives[i])
+        .collect();
+    let keys: Vec<&str> = sampled.iter().map(|&i| deduped.keys[i].as_str()).collect();
+    let n = reps.len();
+
+    // One dense pass over the sample; every later distance is a lookup.
+    let matrix = similarity_matrix(&reps, &|a: &&V, b: &&V| config.distance(a, b));
+    let lookup = |i: &usize, j: &usize| {
+        let (i, j) = (*i.min(j), *i.max(j));
+        Distance::clamped(matrix[condensed_index(n, i, j)])
+    };
+    let true_pairs: Vec<(usize, usize)> = (0..n)
+        .flat_map(|i| ((i + 1)..n).map(move |j| (i, j)))
+        .filter(|&(i, j)| config.threshold.within(lookup(&i, &j).value()))
+        .collect();
+
+    let candidates: Vec<(usize, usize)> = match &config.blocking {
+        // Dense: every pair is a candidate.
+        Blocking::Dense => (0..n)
+            .flat_map(|i| ((i + 1)..n).map(move |j| (i, j)))
+            .collect(),
+        _ => candidate_pairs(&reps, &keys, config).unwrap_or_default(),
+    };
+
+    let indices: Vec<usize> = (0..n).collect();
+    let dense = cluster(&indices, lookup, config.threshold.clone(), config.method);
+    let blocked = cluster_with_candidates(
+        &indices,
+        &candidates,
+        lookup,
+        config.threshold.clone(),
+        config.method,
+    );
+
+    let suggested_tau = match (&config.blocking, &config.gram_bound) {
+        (Blocking::QGram { options, .. }, None) => {
+            (0..=10).rev().map(|step| step as f64 / 20.0).find(|&tau| {
+                let blocking = Blocking::QGram {
+                    tau,
+                    options: options.clone(),
+                };
+                let c = blocking.candidates(&reps).unwrap_or_default();
+                pair_recall(&true_pairs, &c, n).recall() >= target_recall
+            })
+        }
+        _ => None,
+    };
+
+    BlockingReport {
+        candidate_pairs: candidates.len(),
+        total_pairs: n * n.saturating_sub(1) / 2,
+        recall: pair_recall(&true_pairs, &candidates, n),
+        adjusted_rand_index: adjusted_rand_index(&labels(&dense, n), &labels(&blocked, n)),
+        suggested_tau,
+    }
+}
+
+fn pair_recall(
+    true_pairs: &[(usize, usize)],
+    candidates: &[(usize, usize)],
+    n: usize,
+) -> RecallEstimate {
+    let candidates: HashSet<&(usize, usize)> = candidates.iter().collect();
+    RecallEstimate {
+        sampled: n,
+        true_matches: true_pairs.len(),
+        lost: true_pairs
+            .iter()
+            .filter(|p| !candidates.contains(p))
+            .count(),
+    }
+}
+
+/// Position of `(i, j)`, `i < j`, in a row-major condensed upper triangle.
+fn condensed_index(n: usize, i: usize, j: usize) -> usize {
+    i * n - i * (i + 1) / 2 + (j - i - 1)
+}
+
+/// Cluster label per item; unmatched items each get their own label.
+fn labels(clusters: &Clusters, n: usize) -> Vec<usize> {
+    let mut labels = vec![0; n];
+    let mut next = 0;
+    for group in &clusters.matched {
+        for &i in group {
+            labels[i] = next;
+        }
+        next += 1;
+    }
+    for &i in &clusters.unmatched {
+        labels[i] = next;
+        next += 1;
+    }
+    labels
+}
+
+/// Hubert–Arabie adjusted Rand index between two labelings of the same
+/// items.
+fn adjusted_rand_index(a: &[usize], b: &[usize]) -> f64 {
+    fn pairs(k: usize) -> f64 {
+        (k * k.saturating_sub(1) / 2) as f64
+    }
+
+    let mut contingency: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut rows: HashMap<usize, usize> = HashMap::new();
+    let mut cols: HashMap<usize, usize> = HashMap::new();
+    for (&x, &y) in a.iter().zip(b) {
+        *contingency.entry((x, y)).or_insert(0) += 1;
+        *rows.entry(x).or_insert(0) += 1;
+        *cols.entry(y).or_insert(0) += 1;
+    }
+
+    let index: f64 = contingency.values().map(|&k| pairs(k)).sum();
+    let row_sum: f64 = rows.values().map(|&k| pairs(k)).sum();
+    let col_sum: f64 = cols.values().map(|&k| pairs(k)).sum();
+    let expected = row_sum * col_sum / pairs(a.len()).max(1.0);
+    let max = (row_sum + col_sum) / 2.0;
+    if (max - expected).abs() < f64::EPSILON {
+        1.0
+    } else {
+        (index - expected) / (max - expected)
+    }
+}
+
+/// Up to `sample` indices in `0..n`, evenly spaced and ascending.
+pub(crate) fn sample_indices(n: usize, sample: usize) -> Vec<usize> {
+    if sample >= n {
+        return (0..n).collect();
+    }
+    (0..sample).map(|k| k * n / sample).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Blocking;
+    use std::convert::TryInto;
+
+    #[test]
+    fn dense_blocking_loses_nothing() {
+        let values = vec!["Jane", "June", "Joan", "Joseph", "Henry", "Harry"];
+        let config = Config::jaro_winkler(0.3_f64.try_into().unwrap());
+        let estimate = estimate_recall(&values, &config, 100);
+        assert_eq!(estimate.sampled, 6);
+        assert!(estimate.true_matches > 0);
+        assert_eq!(estimate.lost, 0);
+        assert_eq!(estimate.recall(), 1.0);
+    }
+
+    #[test]
+    fn reports_pairs_a_strict_strategy_drops() {
+        let values = vec!["Jane", "June", "Joan", "Joseph", "Henry", "Harry"];
+        let config = Config::jaro_winkler(0.3_f64.try_into().unwrap())
+            .with_blocking_strategy(Blocking::SortedNeighborhood { window: 1 });
+        let estimate = estimate_recall(&values, &config, 100);
+        assert_eq!(estimate.lost, estimate.true_matches);
+        assert_eq!(estimate.recall(), 0.0);
+    }
+
+    #[test]
+    fn adjusted_rand_index_bounds() {
+        assert_eq!(adjusted_rand_index(&[0, 0, 1, 1], &[5, 5, 7, 7]), 1.0);
+        assert!(adjusted_rand_index(&[0, 0, 1, 1], &[0, 1, 0, 1]) < 0.0);
+    }
+
+    #[test]
+    fn blocking_report_compares_partitions() {
+        let values = vec![
+            "Henry", "Jane", "June", "Joan", "José", "Barry", "Joseph", "Mary", "Harry",
+        ];
+        let config = Config::jaro_winkler(0.5_f64.try_into().unwrap()).with_blocking(0.0);
+        let report = blocking_report(&values, &config, 100, 0.95);
+
+        assert_eq!(report.total_pairs, 36);
+        assert_eq!(report.recall.lost, 0);
+        assert_eq!(report.adjusted_rand_index, 1.0);
+        assert!(report.suggested_tau.is_some());
+    }
+
+    #[test]
+    fn lossless_edit_distance_blocking_suggests_no_tau() {
+        let values = vec!["disk full", "disk ful", "timeout", "time out"];
+        let config = Config::levenshtein(0.2_f64.try_into().unwrap()).with_blocking(0.3);
+        let report = blocking_report(&values, &config, 100, 0.95);
+
+        assert_eq!(report.recall.lost, 0);
+        assert_eq!(report.suggested_tau, None);
+    }
+}
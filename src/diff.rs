@@ -0,0 +1,152 @@
+//! Compare the clusters of two runs.
+//!
+//! Clusters are matched across runs by their representatives with [`link`],
+//! so the configured metric, normalizer and blocking decide what counts as
+//! "the same family". Each cluster of the later run is then new, grown,
+//! shrunk or unchanged, and clusters of the earlier run nothing matched are
+//! gone.
+
+use crate::{link, Config, Distance};
+
+/// How a cluster changed between two runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// Only in the later run.
+    New,
+    /// Only in the earlier run.
+    Gone,
+    /// Larger by more than the tolerance.
+    Grew,
+    /// Smaller by more than the tolerance.
+    Shrank,
+    /// Within the tolerance of its earlier size.
+    Unchanged,
+}
+
+/// One entry of [`diff_results`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterChange {
+    /// What happened to the cluster.
+    pub change: Change,
+    /// Index into `before`, unless the cluster is new.
+    pub before: Option<usize>,
+    /// Index into `after`, unless the cluster is gone.
+    pub after: Option<usize>,
+    /// Size in the earlier run (0 if new).
+    pub before_size: usize,
+    /// Size in the later run (0 if gone).
+    pub after_size: usize,
+    /// Distance between the matched representatives.
+    pub distance: Option<Distance>,
+}
+
+/// Match the clusters of two runs, given as `(representative, size)`, and
+/// classify each.
+///
+/// Every `after` cluster is matched to its closest `before` representative
+/// within `config`'s threshold and counts as grown or shrunk when its size
+/// differs by more than `tolerance` (a fraction: `0.2` is 20%) of the
+/// earlier size. Several `after` clusters may match the same `before`
+/// cluster when a family split; each is compared with the whole earlier
+/// size. Results list the `after` clusters in order, then the gone ones.
+///
+/// ```
+/// use group_similar::{diff_results, Change, Config};
+/// use std::convert::TryInto;
+///
+/// let before = [("timeout talking to db", 100), ("disk full on /var", 5)];
+/// let after = [("timeout talking to db!", 300), ("nil has no method price", 40)];
+/// let config = Config::jaro_winkler(0.1_f64.try_into().unwrap());
+/// let changes: Vec<Change> = diff_results(&before, &after, &config, 0.2)
+///     .iter()
+///     .map(|c| c.change)
+///     .collect();
+/// assert_eq!(changes, vec![Change::Grew, Change::New, Change::Gone]);
+/// ```
+pub fn diff_results<V>(
+    before: &[(V, usize)],
+    after: &[(V, usize)],
+    config: &Config<V>,
+    tolerance: f64,
+) -> Vec<ClusterChange>
+where
+    V: AsRef<str> + Sync + Clone,
+{
+    let left: Vec<V> = after.iter().map(|(v, _)| v.clone()).collect();
+    let right: Vec<V> = before.iter().map(|(v, _)| v.clone()).collect();
+    let matches = link(&left, &right, config, 1);
+
+    let mut matched = vec![false; before.len()];
+    let mut changes: Vec<ClusterChange> = after
+        .iter()
+        .zip(matches)
+        .enumerate()
+        .map(|(a, ((_, after_size), found))| match found.first() {
+            Some(&(b, distance)) => {
+                matched[b] = true;
+                let before_size = before[b].1;
+                let delta = (*after_size as f64 - before_size as f64) / before_size.max(1) as f64;
+                let change = if delta > tolerance {
+                    Change::Grew
+                } else if delta < -tolerance {
+                    Change::Shrank
+                } else {
+                    Change::Unchanged
+                };
+                ClusterChange {
+                    change,
+                    before: Some(b),
+                    after: Some(a),
+                    before_size,
+                    after_size: *after_size,
+                    distance: Some(distance),
+                }
+            }
+            None => ClusterChange {
+                change: Change::New,
+                before: None,
+                after: Some(a),
+                before_size: 0,
+                after_size: *after_size,
+                distance: None,
+            },
+        })
+        .collect();
+
+    changes.extend(before.iter().enumerate().filter(|(b, _)| !matched[*b]).map(
+        |(b, (_, before_size))| ClusterChange {
+            change: Change::Gone,
+            before: Some(b),
+            after: None,
+            before_size: *before_size,
+            after_size: 0,
+            distance: None,
+        },
+    ));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn sizes_within_tolerance_are_unchanged() {
+        let before = vec![("connection reset by peer 10.0.0.1".to_string(), 100)];
+        let after = vec![
+            ("connection reset by peer 10.0.0.2".to_string(), 110),
+            ("connection reset by peer 10.0.0.3".to_string(), 50),
+        ];
+        let config = Config::jaro_winkler(0.1_f64.try_into().unwrap())
+            .with_normalizer(crate::normalize::default_normalizer());
+        let changes = diff_results(&before, &after, &config, 0.2);
+
+        // Same normalized key: both match the earlier cluster exactly.
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].change, Change::Unchanged);
+        assert_eq!(changes[0].distance, Some(Distance::MIN));
+        assert_eq!(changes[1].change, Change::Shrank);
+        assert_eq!((changes[1].before, changes[1].after), (Some(0), Some(1)));
+    }
+}
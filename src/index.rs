@@ -0,0 +1,215 @@
+//! Query-time matching against a fixed corpus.
+//!
+//! [`SimilarityIndex`] does the per-corpus work of the string pipeline once —
+//! normalization, the fitted TF-IDF vectors of the cosine metrics, the parsed
+//! frames of the stack-trace metric, and the q-gram index — so that each new
+//! string is only compared against the corpus records that could be within
+//! threshold, without reclustering.
+
+use crate::blocking::QGramIndex;
+use crate::{stacktrace, Blocking, Config, Distance, Frame};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// A corpus indexed under a [`Config`] for nearest-match lookups.
+///
+/// A query and a corpus record with the same normalized key are at distance
+/// zero, exactly as [`crate::group_similar`] treats them as duplicates;
+/// other pairs are scored by the configured metric on the original strings.
+/// With [`Blocking::QGram`] only corpus records sharing the query's key or
+/// passing the q-gram count filter are scored; every other strategy (and the
+/// edit-distance metric, whose exact bound is defined on pairs rather than
+/// probes) scans the whole corpus.
+pub struct SimilarityIndex {
+    config: Config<String>,
+    records: Vec<String>,
+    keys: Vec<String>,
+    /// Corpus indices per normalized key, so exact matches never depend on
+    /// the q-gram filter.
+    by_key: HashMap<String, Vec<usize>>,
+    vectors: Option<Vec<Vec<(u32, f32)>>>,
+    traces: Option<Vec<Vec<Frame>>>,
+    qgrams: Option<(QGramIndex, f64)>,
+}
+
+impl SimilarityIndex {
+    /// Index `corpus` under `config`. Indices returned by
+    /// [`SimilarityIndex::query`] refer to positions in `corpus`.
+    pub fn new<S: AsRef<str>>(corpus: &[S], config: Config<String>) -> Self {
+        let records: Vec<String> = corpus.iter().map(|s| s.as_ref().to_string()).collect();
+        let keys: Vec<String> = records.iter().map(|r| (config.normalize)(r)).collect();
+        let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            by_key.entry(key.clone()).or_default().push(i);
+        }
+        let vectors = config
+            .cosine
+            .as_ref()
+            .map(|cd| records.par_iter().map(|r| cd.vectorize(r)).collect());
+        let traces = config
+            .frames
+            .as_ref()
+            .map(|parser| records.par_iter().map(|r| parser.frames(r)).collect());
+        let qgrams = match &config.blocking {
+            Blocking::QGram { tau, options } if config.gram_bound.is_none() => {
+                let mut index = QGramIndex::with_options(options.clone());
+                for r in &records {
+                    index.insert(r);
+                }
+                Some((index, *tau))
+            }
+            _ => None,
+        };
+
+        Self {
+            config,
+            records,
+            keys,
+            by_key,
+            vectors,
+            traces,
+            qgrams,
+        }
+    }
+
+    /// Number of indexed corpus records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the corpus is empty.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The corpus record at `index`.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.records.get(index).map(String::as_str)
+    }
+
+    /// Up to `k` corpus records within the configured threshold of `query`,
+    /// closest first. Ties are broken by corpus index.
+    pub fn query(&self, query: &str, k: usize) -> Vec<(usize, Distance)> {
+        let key = (self.config.normalize)(query);
+        let owned = query.to_string();
+        let vector = self.config.cosine.as_ref().map(|cd| cd.vectorize(query));
+        let frames = self
+            .config
+            .frames
+            .as_ref()
+            .map(|parser| parser.frames(query));
+
+        let score = |i: usize| {
+            if self.keys[i] == key {
+                return Distance::MIN;
+            }
+            match (&self.vectors, &vector, &self.traces, &frames) {
+                (Some(vectors), Some(v), _, _) => {
+                    Distance::clamped(1.0 - crate::tokens::sparse_cosine(v, &vectors[i]))
+                }
+                (_, _, Some(traces), Some(f)) => {
+                    stacktrace::trace_distance(query, f, &self.records[i], &traces[i])
+                }
+                _ => (self.config.compare)(&owned, &self.records[i]),
+            }
+        };
+
+        let candidates: Vec<usize> = match &self.qgrams {
+            Some((index, tau)) => {
+                let mut candidates = index.candidates(query, *tau);
+                if let Some(exact) = self.by_key.get(&key) {
+                    candidates.extend(exact);
+                    candidates.sort_unstable();
+                    candidates.dedup();
+                }
+                candidates
+            }
+            None => (0..self.records.len()).collect(),
+        };
+
+        let mut matches: Vec<(usize, Distance)> = candidates
+            .into_par_iter()
+            .map(|i| (i, score(i)))
+            .filter(|(_, d)| self.config.threshold.within(d.value()))
+            .collect();
+        matches.sort_by(|a, b| {
+            a.1.partial_cmp(&b.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.cmp(&b.0))
+        });
+        matches.truncate(k);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn merchants() -> Vec<String> {
+        [
+            "McDonalds 105109",
+            "Target ID1244",
+            "Amazon.com TID120159120",
+            "Starbucks Coffee 4411",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn query_returns_matches_within_threshold_closest_first() {
+        let config = Config::jaro_winkler(0.2_f64.try_into().unwrap());
+        let index = SimilarityIndex::new(&merchants(), config);
+
+        let matches = index.query("Target ID1299", 5);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(index.get(matches[0].0), Some("Target ID1244"));
+
+        assert!(index.query("zzzzzz", 5).is_empty());
+    }
+
+    #[test]
+    fn normalized_duplicates_are_exact_matches() {
+        let config = Config::jaro_winkler(0.1_f64.try_into().unwrap())
+            .with_normalizer(crate::normalize::default_normalizer());
+        let index = SimilarityIndex::new(&["order 12345 failed", "order failed"], config);
+
+        let matches = index.query("order 99 failed", 1);
+        assert_eq!(matches[0].0, 0);
+        assert_eq!(matches[0].1.value(), 0.0);
+    }
+
+    #[test]
+    fn normalized_duplicates_bypass_the_qgram_filter() {
+        // The normalizer maps both to "order failed", but the raw strings
+        // share too few grams for the count filter.
+        let config = Config::jaro_winkler(0.1_f64.try_into().unwrap())
+            .with_normalizer(crate::normalize::default_normalizer())
+            .with_blocking(0.9);
+        let index = SimilarityIndex::new(&["order 12345678 failed"], config);
+
+        assert_eq!(index.query("order 9 failed", 1), vec![(0, Distance::MIN)]);
+    }
+
+    #[test]
+    fn indexed_cosine_query_agrees_with_scan() {
+        let corpus = merchants();
+        let threshold: crate::Threshold = 0.6_f64.try_into().unwrap();
+        let scan = SimilarityIndex::new(
+            &corpus,
+            Config::token_cosine(&corpus, threshold.clone()).without_blocking(),
+        );
+        let indexed = SimilarityIndex::new(
+            &corpus,
+            Config::token_cosine(&corpus, threshold).with_blocking(0.1),
+        );
+
+        for q in ["Starbucks Coffee 4412", "McDonalds 105110"] {
+            assert_eq!(scan.query(q, 3), indexed.query(q, 3));
+            assert!(!scan.query(q, 3).is_empty());
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! Reading records from files and streams.
+//!
+//! [`open_input`] and [`decompress`] undo gzip or zstd compression, detected
+//! from the stream's leading bytes rather than the file name, so rotated
+//! `app.log.1.gz` files and piped `zstdcat`-less input both work.
+//! [`read_records`] then tags each line with where it came from as a
+//! [`Sourced`] record, which clusters like the string it wraps.
+//! [`read_records_with`] frames records differently — blank-line or NUL
+//! separated, or starting at lines matching a pattern, optionally with
+//! indented continuation lines — so a whole stack trace is one record.
+
+use regex::Regex;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// A record with the file and 1-based line it was read from.
+///
+/// Compares by value first, so the same text from two places stays two
+/// records.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Sourced<V> {
+    /// The record.
+    pub value: V,
+    /// File it was read from.
+    pub source: Arc<Path>,
+    /// Line number of its first line, counting from 1.
+    pub line: usize,
+}
+
+impl<V: AsRef<str>> AsRef<str> for Sourced<V> {
+    fn as_ref(&self) -> &str {
+        self.value.as_ref()
+    }
+}
+
+/// Wrap `reader` in a gzip or zstd decoder if its first bytes are the
+/// format's magic number; otherwise return it as is. Concatenated gzip
+/// members and zstd frames are read through to the end.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let head = reader.fill_buf()?;
+    if head.starts_with(GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(reader),
+        )))
+    } else if head.starts_with(ZSTD_MAGIC) {
+        Ok(Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Open the file at `path` for reading, decompressing it if needed.
+pub fn open_input(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    decompress(BufReader::new(std::fs::File::open(path)?))
+}
+
+/// Every line of `reader` as a record tagged with `source`, read lazily.
+pub fn read_records<R: BufRead>(
+    reader: R,
+    source: &Path,
+) -> impl Iterator<Item = io::Result<Sourced<String>>> {
+    read_records_with(reader, source, Framing::default())
+}
+
+/// What ends one record and begins the next.
+#[derive(Debug, Clone, Default)]
+pub enum Separator {
+    /// Every line is a record.
+    #[default]
+    Line,
+    /// Records are separated by one or more blank lines.
+    BlankLine,
+    /// Records are separated by NUL bytes and may span lines.
+    Nul,
+    /// A line matching the pattern begins a record; other lines continue
+    /// the current one. Usually anchored at a log line's leading timestamp.
+    Start(Regex),
+}
+
+impl FromStr for Separator {
+    type Err = regex::Error;
+
+    /// `line`, `blank`, `nul`, or a pattern for [`Separator::Start`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "line" => Ok(Self::Line),
+            "blank" => Ok(Self::BlankLine),
+            "nul" => Ok(Self::Nul),
+            pattern => Regex::new(pattern).map(Self::Start),
+        }
+    }
+}
+
+/// How [`read_records_with`] splits input into records.
+#[derive(Debug, Clone, Default)]
+pub struct Framing {
+    /// Record boundaries.
+    pub separator: Separator,
+    /// Append lines starting with whitespace or `at ` — stack frames,
+    /// wrapped messages — to the record before them instead of starting a
+    /// new one, along with the exception line that ends a Python traceback's
+    /// `File "…"` frames. Ignored for [`Separator::Nul`] and
+    /// [`Separator::BlankLine`].
+    pub continuation: bool,
+}
+
+/// Records of `reader` framed by `framing`, tagged with `source` and the
+/// line each one starts on, read lazily. A record's lines are joined with
+/// `\n`.
+///
+/// ```
+/// use group_similar::{read_records_with, Framing, Separator};
+/// use std::path::Path;
+///
+/// let log = "NoMethodError: undefined method 'price'\n\tat app/cart.rb:12\n\tat app/checkout.rb:40\nTimeout\n";
+/// let framing = Framing { separator: Separator::Line, continuation: true };
+/// let records: Vec<_> = read_records_with(log.as_bytes(), Path::new("app.log"), framing)
+///     .map(Result::unwrap)
+///     .collect();
+/// assert_eq!(records.len(), 2);
+/// assert!(records[0].value.ends_with("checkout.rb:40"));
+/// assert_eq!(records[1].line, 4);
+/// ```
+pub fn read_records_with<R: BufRead>(
+    reader: R,
+    source: &Path,
+    framing: Framing,
+) -> impl Iterator<Item = io::Result<Sourced<String>>> {
+    Records {
+        reader,
+        source: Arc::from(source),
+        framing,
+        line: 0,
+        pending: None,
+        traceback: false,
+        done: false,
+    }
+}
+
+/// Iterator behind [`read_records_with`].
+struct Records<R> {
+    reader: R,
+    source: Arc<Path>,
+    framing: Framing,
+    /// Lines consumed so far.
+    line: usize,
+    /// Record still collecting lines: `(first line, text)`.
+    pending: Option<(usize, String)>,
+    /// The pending record ends in a run of Python `File "…"` frames, so its
+    /// exception line comes next.
+    traceback: bool,
+    done: bool,
+}
+
+impl<R: BufRead> Records<R> {
+    fn record(&self, (line, value): (usize, String)) -> Sourced<String> {
+        Sourced {
+            value,
+            source: Arc::clone(&self.source),
+            line,
+        }
+    }
+
+    /// Next NUL-terminated record, skipping empty ones.
+    fn next_nul(&mut self) -> io::Result<Option<Sourced<String>>> {
+        loop {
+            let mut bytes = Vec::new();
+            if self.reader.read_until(0, &mut bytes)? == 0 {
+                return Ok(None);
+            }
+            if bytes.last() == Some(&0) {
+                bytes.pop();
+            }
+            let start = self.line + 1;
+            self.line += bytes.iter().filter(|&&b| b == b'\n').count();
+            let value = String::from_utf8(bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if !value.trim().is_empty() {
+                return Ok(Some(self.record((start, value))));
+            }
+        }
+    }
+
+    /// Next record of the line-based separators.
+    fn next_lines(&mut self) -> io::Result<Option<Sourced<String>>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(self.pending.take().map(|p| self.record(p)));
+            }
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            self.line += 1;
+
+            let continues = self.framing.continuation && is_continuation(&line);
+            let starts = match &self.framing.separator {
+                Separator::BlankLine if line.trim().is_empty() => match self.pending.take() {
+                    Some(p) => return Ok(Some(self.record(p))),
+                    None => continue,
+                },
+                Separator::BlankLine => false,
+                _ if continues => false,
+                _ if self.traceback && !line.trim().is_empty() => false,
+                Separator::Start(pattern) => pattern.is_match(&line),
+                _ => true,
+            };
+            self.traceback =
+                continues && (self.traceback || line.trim_start().starts_with("File \""));
+
+            match &mut self.pending {
+                Some((_, text)) if !starts => {
+                    text.push('\n');
+                    text.push_str(&line);
+                }
+                _ => {
+                    let finished = self.pending.replace((self.line, line));
+                    if let Some(p) = finished {
+                        return Ok(Some(self.record(p)));
+                    }
+                    // A plain line is complete as soon as it is read.
+                    if let (Separator::Line, false) =
+                        (&self.framing.separator, self.framing.continuation)
+                    {
+                        return Ok(self.pending.take().map(|p| self.record(p)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Sourced<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = match self.framing.separator {
+            Separator::Nul => self.next_nul(),
+            _ => self.next_lines(),
+        };
+        match next {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Whether `line` continues the record before it: an indented line or a
+/// stack frame.
+fn is_continuation(line: &str) -> bool {
+    line.starts_with(char::is_whitespace) || line.starts_with("at ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    fn read_all(bytes: &[u8]) -> String {
+        let mut out = String::new();
+        decompress(bytes).unwrap().read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn detects_compression_from_content() {
+        let text = "timeout talking to db\ndisk full\n";
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(text.as_bytes()).unwrap();
+        let gz = gz.finish().unwrap();
+        let zst = zstd::stream::encode_all(text.as_bytes(), 0).unwrap();
+
+        assert_eq!(read_all(text.as_bytes()), text);
+        assert_eq!(read_all(&gz), text);
+        assert_eq!(read_all(&[gz.clone(), gz].concat()), text.repeat(2));
+        assert_eq!(read_all(&zst), text);
+        assert_eq!(read_all(b""), "");
+    }
+
+    fn framed(input: &str, framing: Framing) -> Vec<(usize, String)> {
+        read_records_with(input.as_bytes(), Path::new("-"), framing)
+            .map(|r| r.map(|r| (r.line, r.value)))
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn separators_frame_multi_line_records() {
+        let blank = Framing {
+            separator: Separator::BlankLine,
+            continuation: false,
+        };
+        assert_eq!(
+            framed("\na\nb\n\n  \nc\n", blank),
+            vec![(2, "a\nb".to_string()), (6, "c".to_string())]
+        );
+
+        let nul = Framing {
+            separator: Separator::Nul,
+            continuation: false,
+        };
+        assert_eq!(
+            framed("a\nb\0c\0\n", nul),
+            vec![(1, "a\nb".to_string()), (2, "c".to_string())]
+        );
+
+        let start = Framing {
+            separator: "^\\d{4}-".parse().unwrap(),
+            continuation: false,
+        };
+        assert_eq!(
+            framed(
+                "preamble\n2026-01-01 boom\nCaused by: x\n2026-01-02 ok\n",
+                start
+            ),
+            vec![
+                (1, "preamble".to_string()),
+                (2, "2026-01-01 boom\nCaused by: x".to_string()),
+                (4, "2026-01-02 ok".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn continuation_lines_join_the_previous_record() {
+        let input = "Traceback (most recent call last):\n  File \"app.py\", line 3\n    return order['id']\nKeyError: 'id'\nat top\nafter\n\nnext\n";
+        let continuation = Framing {
+            separator: Separator::Line,
+            continuation: true,
+        };
+        assert_eq!(
+            framed(input, continuation),
+            vec![
+                (
+                    1,
+                    "Traceback (most recent call last):\n  File \"app.py\", line 3\n    return order['id']\nKeyError: 'id'\nat top".to_string()
+                ),
+                (6, "after".to_string()),
+                (7, "".to_string()),
+                (8, "next".to_string()),
+            ]
+        );
+        assert_eq!(framed("a\n\nb", Framing::default()).len(), 3);
+    }
+
+    #[test]
+    fn records_remember_their_line() {
+        let records: Vec<Sourced<String>> =
+            read_records(&b"first\nsecond\n"[..], Path::new("app.log"))
+                .collect::<io::Result<_>>()
+                .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].value, "second");
+        assert_eq!(records[1].line, 2);
+        assert_eq!(&*records[1].source, Path::new("app.log"));
+    }
+}
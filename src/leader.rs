@@ -0,0 +1,192 @@
+//! One-pass leader clustering.
+//!
+//! Each record is compared against the existing leaders in creation order and
+//! joins the first one within threshold; otherwise it becomes a new leader.
+//! Assignments are final the moment a record arrives — nothing is ever
+//! re-clustered — so the algorithm runs in a single pass and only holds the
+//! leaders in memory. The trade-off is order sensitivity: the partition
+//! depends on which records happen to arrive first.
+
+use crate::blocking::QGramIndex;
+use crate::{Clusters, Distance, QGramOptions, Threshold};
+
+/// Where [`Leaders::push`] placed a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assignment {
+    /// The record matched no existing leader and now leads cluster `id`.
+    Leader(usize),
+    /// The record joined the existing cluster `id`.
+    Member(usize),
+}
+
+impl Assignment {
+    /// Cluster id, regardless of whether the record leads it.
+    pub fn cluster(self) -> usize {
+        match self {
+            Assignment::Leader(id) | Assignment::Member(id) => id,
+        }
+    }
+}
+
+/// Streaming leader clusterer.
+///
+/// Holds one record per cluster (its leader). Cluster ids are assigned in
+/// creation order starting at 0. [`Leaders::with_qgram_index`] narrows the
+/// leaders each record is compared against using shared character trigrams.
+pub struct Leaders<T, F> {
+    leaders: Vec<T>,
+    distance: F,
+    threshold: Threshold,
+    index: Option<LeaderIndex>,
+}
+
+struct LeaderIndex {
+    qgrams: QGramIndex,
+    tau_coef: f64,
+}
+
+impl<T, F> Leaders<T, F>
+where
+    T: AsRef<str>,
+    F: Fn(&T, &T) -> Distance,
+{
+    /// Create an empty clusterer. Every record is compared against every
+    /// leader until one is within `threshold`.
+    pub fn new(distance: F, threshold: Threshold) -> Self {
+        Self {
+            leaders: Vec::new(),
+            distance,
+            threshold,
+            index: None,
+        }
+    }
+
+    /// Assign `item` to the first leader within threshold, or make it a new
+    /// leader.
+    pub fn push(&mut self, item: T) -> Assignment {
+        let found = match &self.index {
+            Some(index) => index
+                .qgrams
+                .candidates(item.as_ref(), index.tau_coef)
+                .into_iter()
+                .find(|&id| self.within(id, &item)),
+            None => (0..self.leaders.len()).find(|&id| self.within(id, &item)),
+        };
+
+        match found {
+            Some(id) => Assignment::Member(id),
+            None => {
+                let id = self.leaders.len();
+                if let Some(index) = &mut self.index {
+                    index.qgrams.insert(item.as_ref());
+                }
+                self.leaders.push(item);
+                Assignment::Leader(id)
+            }
+        }
+    }
+
+    /// Leaders in cluster-id order.
+    pub fn leaders(&self) -> &[T] {
+        &self.leaders
+    }
+
+    fn within(&self, id: usize, item: &T) -> bool {
+        self.threshold
+            .within((self.distance)(&self.leaders[id], item).value())
+    }
+
+    /// Only compare records against leaders passing the q-gram count filter
+    /// described on [`crate::qgram_candidates`]. Leaders are still tried in
+    /// creation order, so the result matches the unindexed clusterer except
+    /// where the filter drops a within-threshold leader.
+    pub fn with_qgram_index(self, tau_coef: f64) -> Self {
+        self.with_qgram_options(tau_coef, QGramOptions::default())
+    }
+
+    /// [`Leaders::with_qgram_index`] with the gram length, padding, case
+    /// folding and short-record minimum of `options`. `common_cutoff` is
+    /// ignored: the leader set grows as records arrive.
+    pub fn with_qgram_options(mut self, tau_coef: f64, options: QGramOptions) -> Self {
+        let mut qgrams = QGramIndex::with_options(options);
+        for leader in &self.leaders {
+            qgrams.insert(leader.as_ref());
+        }
+        self.index = Some(LeaderIndex { qgrams, tau_coef });
+        self
+    }
+}
+
+/// Run leader clustering over `items` in slice order.
+///
+/// Single pass, no distance matrix: each item is compared against the current
+/// leaders only. Far cheaper than [`crate::cluster`] when clusters are few,
+/// but the partition depends on input order and a cluster's members are only
+/// guaranteed to be within `threshold` of its leader, not of each other.
+pub fn leader_cluster<T, F>(items: &[T], distance: F, threshold: Threshold) -> Clusters
+where
+    F: Fn(&T, &T) -> Distance,
+{
+    // Each group's first index is its leader.
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+
+    for i in 0..items.len() {
+        let found = groups
+            .iter()
+            .position(|group| threshold.within(distance(&items[group[0]], &items[i]).value()));
+        match found {
+            Some(id) => groups[id].push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+
+    let mut clusters = Clusters::default();
+    for group in groups {
+        if group.len() == 1 {
+            clusters.unmatched.push(group[0]);
+        } else {
+            clusters.matched.push(group);
+        }
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn leader_cluster_joins_first_leader_within_threshold() {
+        let items = vec![0.0_f32, 0.9, 0.05, 0.95, 0.5];
+        let result = leader_cluster(
+            &items,
+            |a: &f32, b: &f32| Distance::clamped((a - b).abs()),
+            0.2_f64.try_into().unwrap(),
+        );
+
+        assert_eq!(result.matched, vec![vec![0, 2], vec![1, 3]]);
+        assert_eq!(result.unmatched, vec![4]);
+    }
+
+    #[test]
+    fn qgram_index_matches_linear_scan_on_separable_strings() {
+        let distance =
+            |a: &&str, b: &&str| Distance::clamped(1.0 - jaro_winkler::jaro_winkler(a, b));
+        let values = vec![
+            "connection refused by upstream host alpha",
+            "connection refused by upstream host beta",
+            "template missing for storefront product page",
+            "connection refused by upstream host gamma",
+            "template missing for storefront cart page",
+        ];
+
+        let mut linear = Leaders::new(distance, 0.2_f64.try_into().unwrap());
+        let mut indexed = Leaders::new(distance, 0.2_f64.try_into().unwrap()).with_qgram_index(0.2);
+
+        for v in &values {
+            assert_eq!(linear.push(*v), indexed.push(*v));
+        }
+        assert_eq!(indexed.leaders().len(), 2);
+    }
+}
@@ -7,12 +7,26 @@
 //! - **Layer 1 (metric-agnostic):** [`cluster`] and [`cluster_with_candidates`]
 //!   run [complete-linkage hierarchical clustering](https://en.wikipedia.org/wiki/Complete-linkage_clustering)
 //!   over any `&[T]` with a user-supplied distance function. Output is a
-//!   [`Clusters`] of indices into the input slice.
+//!   [`Clusters`] of indices into the input slice. [`leader_cluster`] (and
+//!   its streaming form, [`Leaders`]) is a one-pass alternative for inputs
+//!   too large to hold a distance matrix. [`nearest_neighbors`] returns
+//!   each item's top-k closest items instead of a partition.
 //!
 //! - **Layer 2 (string pipeline):** [`group_similar`] adds normalize → dedup
 //!   → cluster → expand glue for string-bearing records, using a [`Config`]
-//!   that bundles a metric (Jaro-Winkler, IDF-weighted token cosine, …) with
-//!   a threshold, normalizer, and candidate-pair strategy ([`Blocking`]).
+//!   that bundles a metric (Jaro-Winkler, IDF-weighted token cosine, stack
+//!   trace frames, …) with a threshold, normalizer, and candidate-pair
+//!   strategy ([`Blocking`]).
+//!   [`SimilarityIndex`] reuses the same [`Config`] to match new strings
+//!   against a fixed corpus without reclustering, [`link`] matches the
+//!   records of one dataset against another, and [`similar_pairs`] exports
+//!   the raw pairwise scores. [`group_similar_collapsed`] counts duplicates
+//!   instead of listing them. [`cluster_template`] summarizes a cluster as
+//!   its common token skeleton, and [`Timeline`] as when its records
+//!   happened; [`diff_results`] compares the clusters of two runs.
+//!   Pre-aggregated input wraps each record in [`Counted`] and clusters with
+//!   [`group_similar_weighted`]; [`read_records`] reads possibly compressed
+//!   input as [`Sourced`] records that remember their file and line.
 //!
 //! # Example: string pipeline
 //!
@@ -74,16 +88,46 @@
 //! assert_eq!(clusters.unmatched.len(), 0);
 //! ```
 
+mod blocking;
 mod config;
+mod diagnostics;
+mod diff;
+mod edit_distance;
+mod index;
+mod input;
+mod leader;
 pub mod normalize;
+mod stacktrace;
+mod template;
+mod timeline;
 mod tokens;
-
+mod weighted;
+
+pub use blocking::{
+    key_candidates, minhash_candidates, prefix_candidates, qgram_candidates,
+    qgram_candidates_for_edit_distance, qgram_candidates_with_options,
+    sorted_neighborhood_candidates, token_candidates, BlockingKey, QGramOptions, Shingle,
+};
+use config::GramBound;
 pub use config::{Blocking, Config, Threshold};
+pub use diagnostics::{blocking_report, estimate_recall, BlockingReport, RecallEstimate};
+pub use diff::{diff_results, Change, ClusterChange};
+pub use index::SimilarityIndex;
+pub use input::{
+    decompress, open_input, read_records, read_records_with, Framing, Separator, Sourced,
+};
 pub use kodama::Method;
+pub use leader::{leader_cluster, Assignment, Leaders};
+pub use stacktrace::{parse_frames, Frame};
+pub use template::{cluster_template, WILDCARD};
+pub use timeline::{find_timestamp, format_timestamp, parse_timestamp, Timeline};
+pub use weighted::{group_similar_weighted, total_weight, Counted, Weighted};
 
 use kodama::linkage;
-use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use rayon::iter::{
+    IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+use std::collections::{BTreeMap, HashMap};
 
 /// Pairwise distance in `[0.0, 1.0]`. Smaller = more similar.
 ///
@@ -275,6 +319,77 @@ where
     Clusters { matched, unmatched }
 }
 
+/// For each item, its `k` nearest other items by `distance`, closest first.
+///
+/// With `candidates: None` every pair is compared (O(n²) distance calls,
+/// parallelized per item, without materializing a matrix). With a candidate
+/// list — e.g. from [`qgram_candidates`] — only those pairs are compared, so
+/// an item's neighbors are drawn from its candidates and may number fewer
+/// than `k`. Ties are broken by index.
+pub fn nearest_neighbors<T, F>(
+    items: &[T],
+    k: usize,
+    distance: F,
+    candidates: Option<&[(usize, usize)]>,
+) -> Vec<Vec<(usize, Distance)>>
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Distance + Send + Sync,
+{
+    let n = items.len();
+    let by_distance = |a: &(usize, Distance), b: &(usize, Distance)| {
+        a.1.partial_cmp(&b.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    };
+
+    match candidates {
+        None => (0..n)
+            .into_par_iter()
+            .map(|i| {
+                let mut row: Vec<(usize, Distance)> = (0..n)
+                    .filter(|&j| j != i)
+                    .map(|j| (j, distance(&items[i], &items[j])))
+                    .collect();
+                keep_nearest(&mut row, k, by_distance);
+                row
+            })
+            .collect(),
+        Some(candidates) => {
+            let scored: Vec<(usize, usize, Distance)> = candidates
+                .par_iter()
+                .map(|&(i, j)| (i, j, distance(&items[i], &items[j])))
+                .collect();
+            let mut rows: Vec<Vec<(usize, Distance)>> = vec![Vec::new(); n];
+            for (i, j, d) in scored {
+                rows[i].push((j, d));
+                rows[j].push((i, d));
+            }
+            rows.par_iter_mut().for_each(|row| {
+                row.sort_unstable_by_key(|&(j, _)| j);
+                row.dedup_by_key(|(j, _)| *j);
+                keep_nearest(row, k, by_distance);
+            });
+            rows
+        }
+    }
+}
+
+/// Truncate `row` to its `k` smallest entries under `by`, sorted. Only those
+/// `k` are sorted; the rest are partitioned away in linear time.
+fn keep_nearest<F>(row: &mut Vec<(usize, Distance)>, k: usize, by: F)
+where
+    F: Fn(&(usize, Distance), &(usize, Distance)) -> std::cmp::Ordering,
+{
+    if row.len() > k {
+        if k > 0 {
+            row.select_nth_unstable_by(k - 1, &by);
+        }
+        row.truncate(k);
+    }
+    row.sort_by(by);
+}
+
 /// Build a condensed pairwise distance matrix using `compare`, parallelized
 /// with Rayon. Returns the upper triangle in row-major order (`n*(n-1)/2`
 /// entries) as raw `f32`s — the layout expected by kodama. The closure's
@@ -299,27 +414,6 @@ where
         .collect::<Vec<_>>()
 }
 
-/// Generate candidate pairs for [`cluster_with_candidates`] using a character
-/// trigram inverted index.
-///
-/// A pair `(i, j)` is emitted when records `i` and `j` share at least
-/// `max(1, tau_coef × min(unique_trigrams_i, unique_trigrams_j))` trigrams.
-/// Records with fewer than 15 unique trigrams bypass the filter (they don't
-/// have enough signal for the heuristic to be reliable). Very common trigrams
-/// (present in ≥80% of records) are skipped — they don't discriminate and
-/// dominate scan cost.
-///
-/// `tau_coef` controls aggressiveness: higher values reject more pairs but
-/// risk false negatives (within-threshold pairs whose trigram overlap was
-/// below `tau`). Typical range: 0.1–0.4. `cluster_with_candidates` recovers
-/// most false negatives within a component, but pairs that should bridge two
-/// components and don't appear here will fragment the cluster.
-pub fn qgram_candidates<S: AsRef<str>>(items: &[S], tau_coef: f64) -> Vec<(usize, usize)> {
-    let strs: Vec<&str> = items.iter().map(|s| s.as_ref()).collect();
-    let (index, qgram_counts) = build_qgram_index(&strs);
-    generate_candidates(&strs, &index, &qgram_counts, tau_coef)
-}
-
 fn extract_index_clusters(
     dend: &kodama::Dendrogram<f32>,
     n: usize,
@@ -425,96 +519,16 @@ impl UnionFind {
     }
 }
 
-fn qgram3_to_u32(b: &[u8]) -> u32 {
-    (b[0] as u32) | ((b[1] as u32) << 8) | ((b[2] as u32) << 16)
-}
-
-fn build_qgram_index(strs: &[&str]) -> (HashMap<u32, Vec<usize>>, Vec<u32>) {
-    let n = strs.len();
-    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
-    let mut qgram_counts = vec![0u32; n];
-
-    for (i, s) in strs.iter().enumerate() {
-        let bytes = s.as_bytes();
-        if bytes.len() < 3 {
-            continue;
-        }
-        let mut seen: HashSet<u32> = HashSet::new();
-        for w in bytes.windows(3) {
-            let g = qgram3_to_u32(w);
-            if seen.insert(g) {
-                index.entry(g).or_default().push(i);
-            }
-        }
-        qgram_counts[i] = seen.len() as u32;
-    }
-
-    (index, qgram_counts)
-}
-
-fn generate_candidates(
-    strs: &[&str],
-    index: &HashMap<u32, Vec<usize>>,
-    qgram_counts: &[u32],
-    tau_coef: f64,
-) -> Vec<(usize, usize)> {
-    let n = strs.len();
-    let common_threshold = (n * 4) / 5;
-
-    (0..n)
-        .into_par_iter()
-        .flat_map(|i| {
-            let bytes = strs[i].as_bytes();
-            if bytes.len() < 3 {
-                return Vec::new();
-            }
-
-            let mut seen: HashSet<u32> = HashSet::new();
-            let mut counts = vec![0u32; n];
-
-            for w in bytes.windows(3) {
-                let g = qgram3_to_u32(w);
-                if !seen.insert(g) {
-                    continue;
-                }
-                if let Some(bucket) = index.get(&g) {
-                    if bucket.len() > common_threshold {
-                        continue;
-                    }
-                    for &j in bucket {
-                        if j > i {
-                            counts[j] += 1;
-                        }
-                    }
-                }
-            }
-
-            const MIN_QGRAMS_FOR_FILTER: u32 = 15;
-
-            let qi_count = qgram_counts[i];
-            ((i + 1)..n)
-                .filter_map(|j| {
-                    let qj_count = qgram_counts[j];
-                    if qi_count < MIN_QGRAMS_FOR_FILTER || qj_count < MIN_QGRAMS_FOR_FILTER {
-                        return Some((i, j));
-                    }
-                    let min_q = qi_count.min(qj_count) as f64;
-                    let tau = (tau_coef * min_q).max(1.0) as u32;
-                    if counts[j] >= tau {
-                        Some((i, j))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect()
-}
-
 /// Deduplicated records: unique representatives plus a map from normalized
 /// string back to all original records sharing that normalized form.
 struct Deduplicated<'a, V> {
     representatives: Vec<&'a V>,
+    /// Normalized key of each representative, in the same order.
+    keys: Vec<String>,
+    /// Index in the input of each representative, in the same order.
+    positions: Vec<usize>,
+    /// Index into `representatives` of each input record's representative.
+    representative_of: Vec<usize>,
     duplicates: BTreeMap<String, Vec<&'a V>>,
 }
 
@@ -526,22 +540,32 @@ where
     V: AsRef<str>,
 {
     let mut order: Vec<String> = Vec::new();
+    let mut positions: Vec<usize> = Vec::new();
+    let mut representative_of: Vec<usize> = Vec::with_capacity(records.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
     let mut duplicates: BTreeMap<String, Vec<&'a V>> = BTreeMap::new();
 
-    for record in records {
+    for (i, record) in records.iter().enumerate() {
         let key = normalize(record.as_ref());
-        duplicates
-            .entry(key)
-            .or_insert_with_key(|k| {
-                order.push(k.clone());
-                Vec::new()
-            })
-            .push(record);
+        let r = match index.get(&key) {
+            Some(&r) => r,
+            None => {
+                index.insert(key.clone(), order.len());
+                order.push(key.clone());
+                positions.push(i);
+                order.len() - 1
+            }
+        };
+        representative_of.push(r);
+        duplicates.entry(key).or_default().push(record);
     }
 
-    let representatives: Vec<&'a V> = order.iter().map(|k| duplicates[k][0]).collect();
+    let representatives: Vec<&'a V> = positions.iter().map(|&i| &records[i]).collect();
     Deduplicated {
         representatives,
+        keys: order,
+        positions,
+        representative_of,
         duplicates,
     }
 }
@@ -603,13 +627,85 @@ where
     results
 }
 
+/// Like [`expand`], but keep one `(exemplar, count)` per representative
+/// rather than every duplicate.
+fn collapse<'a, V>(
+    clustered: Clustered<'a, V>,
+    duplicates: &BTreeMap<String, Vec<&'a V>>,
+    normalize: &dyn Fn(&str) -> String,
+) -> BTreeMap<&'a V, Vec<(&'a V, usize)>>
+where
+    V: AsRef<str> + Ord,
+{
+    let groups = clustered
+        .matched
+        .into_iter()
+        .chain(clustered.unmatched.into_iter().map(|rep| vec![rep]));
+
+    let mut results = BTreeMap::new();
+    for group in groups {
+        let counted: Vec<(&'a V, usize)> = group
+            .iter()
+            .map(|rep| {
+                let all = &duplicates[&normalize(rep.as_ref())];
+                (all[0], all.len())
+            })
+            .collect();
+        if let Some(&(first, _)) = counted.first() {
+            results.entry(first).or_insert(counted);
+        }
+    }
+    results
+}
+
+/// Candidate pairs over the deduplicated representatives for the configured
+/// blocking strategy, or `None` for the dense pipeline. `keys` are the
+/// representatives' normalized forms, in the same order; only the sorted
+/// neighborhood orders by them; every other strategy reads the strings the
+/// metric compares, whose varying content still carries gram and token
+/// signal after normalization has collapsed it.
+fn candidate_pairs<V, K>(
+    representatives: &[&V],
+    keys: &[K],
+    config: &Config<V>,
+) -> Option<Vec<(usize, usize)>>
+where
+    V: AsRef<str> + Sync,
+    K: AsRef<str> + Sync,
+{
+    candidate_pairs_across(representatives, keys, config, None)
+}
+
+/// [`candidate_pairs`]; with `Some(split)`, only the pairs `(i, j)` with
+/// `i < split <= j`, generated without spending blocks or windows on pairs
+/// within either side.
+fn candidate_pairs_across<V, K>(
+    representatives: &[&V],
+    keys: &[K],
+    config: &Config<V>,
+    split: Option<usize>,
+) -> Option<Vec<(usize, usize)>>
+where
+    V: AsRef<str> + Sync,
+    K: AsRef<str> + Sync,
+{
+    match (&config.blocking, &config.gram_bound) {
+        (Blocking::QGram { options, .. }, Some(GramBound::NormalizedEditDistance)) => Some(
+            blocking::edit_distance_pairs(representatives, &config.threshold, options, split),
+        ),
+        (Blocking::SortedNeighborhood { .. }, _) => config.blocking.candidates_across(keys, split),
+        (blocking, _) => blocking.candidates_across(representatives, split),
+    }
+}
+
 /// Group string-bearing records via normalize → dedup → cluster → expand.
 ///
 /// Candidate-pair generation is controlled by [`Config::with_blocking`] /
 /// [`Config::without_blocking`]; the default is [`Blocking::Dense`] (full
 /// O(n²) matrix, ground-truth partition). For larger inputs where the dense
 /// matrix is expensive, switch to [`Blocking::QGram`] via
-/// `config.with_blocking(tau)`.
+/// `config.with_blocking(tau)`, or another strategy via
+/// [`Config::with_blocking_strategy`].
 pub fn group_similar<'a, V>(records: &'a [V], config: &Config<V>) -> BTreeMap<&'a V, Vec<&'a V>>
 where
     V: std::hash::Hash + AsRef<str> + Eq + Sync + Ord,
@@ -618,84 +714,333 @@ where
         return BTreeMap::new();
     }
 
-    let normalize = &*config.normalize;
-    let deduped = deduplicate(records, normalize);
+    let prepared = Prepared::new(records, config);
+    expand(
+        prepared.clustered(),
+        &prepared.deduped.duplicates,
+        &*config.normalize,
+    )
+}
 
-    if deduped.representatives.len() <= 1 {
-        return expand(
-            Clustered {
-                matched: vec![deduped.representatives],
-                unmatched: vec![],
-            },
-            &deduped.duplicates,
-            normalize,
-        );
+/// [`group_similar`], with each cluster's records counted per normalized key
+/// instead of listed one by one.
+///
+/// Every cluster's vector holds one `(exemplar, count)` per distinct
+/// normalized key among its records, the cluster key included: the
+/// exemplar is the first record with that key and `count` how many records
+/// share it. Entries follow the order [`group_similar`] lists the records in,
+/// so the first is the cluster key's own. A cluster of 5,000 identical lines
+/// is a single entry.
+///
+/// ```
+/// use group_similar::{group_similar_collapsed, normalize, Config};
+/// use std::convert::TryInto;
+///
+/// let records = vec!["timeout after 30s", "timeout after 31s", "timeout after 2s!"];
+/// let config = Config::jaro_winkler(0.1_f64.try_into().unwrap())
+///     .with_normalizer(normalize::default_normalizer());
+/// let results = group_similar_collapsed(&records, &config);
+/// assert_eq!(results[&"timeout after 30s"], vec![(&records[0], 2), (&records[2], 1)]);
+/// ```
+pub fn group_similar_collapsed<'a, V>(
+    records: &'a [V],
+    config: &Config<V>,
+) -> BTreeMap<&'a V, Vec<(&'a V, usize)>>
+where
+    V: std::hash::Hash + AsRef<str> + Eq + Sync + Ord,
+{
+    if records.is_empty() {
+        return BTreeMap::new();
     }
 
-    let candidates: Option<Vec<(usize, usize)>> = match &config.blocking {
-        Blocking::Dense => None,
-        Blocking::QGram { tau } => Some(qgram_candidates(&deduped.representatives, *tau)),
+    let prepared = Prepared::new(records, config);
+    collapse(
+        prepared.clustered(),
+        &prepared.deduped.duplicates,
+        &*config.normalize,
+    )
+}
+
+/// For each deduplicated record, its `k` nearest other records under
+/// `config`'s metric, closest first.
+///
+/// Runs the same normalize → dedup → blocking as [`group_similar`] and then
+/// [`nearest_neighbors`] over the representatives, so duplicates collapse to
+/// one entry and blocking limits which records can be neighbors. Neighbors
+/// are not filtered by the configured threshold.
+pub fn similar_neighbors<'a, V>(
+    records: &'a [V],
+    config: &Config<V>,
+    k: usize,
+) -> BTreeMap<&'a V, Vec<(&'a V, Distance)>>
+where
+    V: AsRef<str> + Sync + Ord,
+{
+    let prepared = Prepared::new(records, config);
+    let reps = &prepared.deduped.representatives;
+    let indices: Vec<usize> = (0..reps.len()).collect();
+    let neighbors = nearest_neighbors(
+        &indices,
+        k,
+        |a: &usize, b: &usize| prepared.distance(*a, *b),
+        prepared.candidates().as_deref(),
+    );
+
+    neighbors
+        .into_iter()
+        .enumerate()
+        .map(|(i, ns)| (reps[i], ns.into_iter().map(|(j, d)| (reps[j], d)).collect()))
+        .collect()
+}
+
+/// Scored candidate pairs under `config`, as `(i, j, distance)` with `i < j`
+/// indices into `records`, in ascending order.
+///
+/// Runs the same normalize → dedup → blocking as [`group_similar`], so pairs
+/// are only formed between representatives (the first record of each
+/// normalized key) and only for the pairs the blocking strategy proposes —
+/// every pair under [`Blocking::Dense`]. With `max_distance`, pairs farther
+/// apart are dropped; the clustering threshold is not applied.
+pub fn similar_pairs<V>(
+    records: &[V],
+    config: &Config<V>,
+    max_distance: Option<&Threshold>,
+) -> Vec<(usize, usize, Distance)>
+where
+    V: AsRef<str> + Sync,
+{
+    let prepared = Prepared::new(records, config);
+    let positions = &prepared.deduped.positions;
+    let m = positions.len();
+    let score = |(a, b): (usize, usize)| {
+        let d = prepared.distance(a, b);
+        let (i, j) = (positions[a], positions[b]);
+        (i.min(j), i.max(j), d)
+    };
+    let keep =
+        |(_, _, d): &(usize, usize, Distance)| max_distance.is_none_or(|max| max.within(d.value()));
+
+    let mut pairs: Vec<(usize, usize, Distance)> = match prepared.candidates() {
+        Some(candidates) => candidates.into_par_iter().map(score).filter(keep).collect(),
+        None => (0..m)
+            .into_par_iter()
+            .flat_map_iter(|a| ((a + 1)..m).map(move |b| (a, b)))
+            .map(score)
+            .filter(keep)
+            .collect(),
+    };
+    pairs.sort_unstable_by_key(|&(i, j, _)| (i, j));
+    pairs
+}
+
+/// Record linkage: for each record in `left`, up to `k` records in `right`
+/// within `config`'s threshold, closest first, as indices into `right`.
+///
+/// Each side is normalized and deduplicated as in [`group_similar`], and
+/// only cross pairs of representatives are scored. Blocking runs over both
+/// sides' representatives together but only generates cross pairs: blocks
+/// are split by side and a [`Blocking::SortedNeighborhood`] window holds
+/// records of the other side alone, so a crowd of similar left records
+/// can't push a right match out of it. A left and right record with the
+/// same normalized key are at distance zero, as duplicates are in
+/// [`group_similar`]. Ties are broken by index.
+pub fn link<V>(left: &[V], right: &[V], config: &Config<V>, k: usize) -> Vec<Vec<(usize, Distance)>>
+where
+    V: AsRef<str> + Sync,
+{
+    let left = Prepared::new(left, config);
+    let right = Prepared::new(right, config);
+    let m = left.deduped.representatives.len();
+    let reps: Vec<&V> = left
+        .deduped
+        .representatives
+        .iter()
+        .chain(&right.deduped.representatives)
+        .copied()
+        .collect();
+    let keys: Vec<String> = left
+        .deduped
+        .keys
+        .iter()
+        .chain(&right.deduped.keys)
+        .cloned()
+        .collect();
+
+    let distance = |a: usize, b: usize| {
+        if keys[a] == keys[m + b] {
+            Distance::MIN
+        } else {
+            left.distance_to(a, &right, b)
+        }
+    };
+
+    let pairs: Vec<(usize, usize)> = match candidate_pairs_across(&reps, &keys, config, Some(m)) {
+        Some(candidates) => {
+            // Blocking sees each side's representatives as ordinary records,
+            // so a key shared across sides is not guaranteed to produce a
+            // candidate; add those pairs directly.
+            let by_key: HashMap<&str, usize> = right
+                .deduped
+                .keys
+                .iter()
+                .enumerate()
+                .map(|(b, key)| (key.as_str(), b))
+                .collect();
+            let mut pairs: Vec<(usize, usize)> = candidates
+                .into_iter()
+                .map(|(a, b)| (a, b - m))
+                .chain(
+                    left.deduped
+                        .keys
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(a, key)| by_key.get(key.as_str()).map(|&b| (a, b))),
+                )
+                .collect();
+            pairs.sort_unstable();
+            pairs.dedup();
+            pairs
+        }
+        None => (0..m)
+            .flat_map(|a| (0..right.deduped.representatives.len()).map(move |b| (a, b)))
+            .collect(),
     };
 
-    let clusters = if let Some(cd) = &config.cosine {
-        // Fast path: vectorize each representative once, then cluster over the
-        // cached sparse vectors. Avoids re-vectorizing both sides of every
-        // pairwise comparison, which dominates the runtime for cosine metrics
-        // with non-trivial corpora.
-        let vocab = &*cd.vocab;
-        let idf = &*cd.idf;
-        let vectors: Vec<Vec<(u32, f32)>> = if cd.positional {
+    let scored: Vec<(usize, usize, Distance)> = pairs
+        .into_par_iter()
+        .map(|(a, b)| (a, b, distance(a, b)))
+        .filter(|(_, _, d)| config.threshold.within(d.value()))
+        .collect();
+
+    // Every right record shares its representative's distance.
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); right.deduped.representatives.len()];
+    for (j, &b) in right.deduped.representative_of.iter().enumerate() {
+        members[b].push(j);
+    }
+    let mut rows: Vec<Vec<(usize, Distance)>> = vec![Vec::new(); m];
+    for (a, b, d) in scored {
+        rows[a].extend(members[b].iter().map(|&j| (j, d)));
+    }
+    rows.par_iter_mut().for_each(|row| {
+        row.sort_by(|a, b| {
+            a.1.partial_cmp(&b.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.cmp(&b.0))
+        });
+        row.truncate(k);
+    });
+    left.deduped
+        .representative_of
+        .iter()
+        .map(|&a| rows[a].clone())
+        .collect()
+}
+
+/// Deduplicated records plus the metric's per-representative precompute —
+/// the shared front half of every string-pipeline entry point.
+struct Prepared<'a, 'c, V> {
+    config: &'c Config<V>,
+    deduped: Deduplicated<'a, V>,
+    /// One cached TF-IDF vector per representative when the metric is a
+    /// cosine variant. Avoids re-vectorizing both sides of every pairwise
+    /// comparison, which dominates the runtime for cosine metrics with
+    /// non-trivial corpora.
+    vectors: Option<Vec<Vec<(u32, f32)>>>,
+    /// One parsed frame list per representative under
+    /// [`Config::stack_trace`].
+    traces: Option<Vec<Vec<Frame>>>,
+}
+
+impl<'a, 'c, V> Prepared<'a, 'c, V>
+where
+    V: AsRef<str> + Sync,
+{
+    fn new(records: &'a [V], config: &'c Config<V>) -> Self {
+        let deduped = deduplicate(records, &*config.normalize);
+        let vectors = config.cosine.as_ref().map(|cd| {
             deduped
                 .representatives
                 .par_iter()
-                .map(|r| crate::tokens::vectorize_positional(r.as_ref(), vocab, idf))
+                .map(|r| cd.vectorize(r.as_ref()))
                 .collect()
-        } else {
+        });
+        let traces = config.frames.as_ref().map(|parser| {
             deduped
                 .representatives
                 .par_iter()
-                .map(|r| crate::tokens::vectorize(r.as_ref(), vocab, idf))
+                .map(|r| parser.frames(r.as_ref()))
                 .collect()
-        };
-        let distance = |a: &Vec<(u32, f32)>, b: &Vec<(u32, f32)>| {
-            Distance::clamped(1.0 - crate::tokens::sparse_cosine(a, b))
-        };
-        match &candidates {
-            None => cluster(&vectors, distance, config.threshold.clone(), config.method),
-            Some(c) => cluster_with_candidates(
-                &vectors,
-                c,
-                distance,
-                config.threshold.clone(),
-                config.method,
-            ),
+        });
+        Self {
+            config,
+            deduped,
+            vectors,
+            traces,
         }
-    } else {
-        let distance = |a: &&V, b: &&V| (config.compare)(*a, *b);
-        match &candidates {
-            None => cluster(
-                &deduped.representatives,
-                distance,
-                config.threshold.clone(),
-                config.method,
-            ),
+    }
+
+    /// Distance between representatives `i` and `j`.
+    fn distance(&self, i: usize, j: usize) -> Distance {
+        self.distance_to(i, self, j)
+    }
+
+    /// Distance between representative `i` and representative `j` of
+    /// `other`, prepared under the same config.
+    fn distance_to(&self, i: usize, other: &Prepared<'_, '_, V>, j: usize) -> Distance {
+        let (a, b) = (
+            self.deduped.representatives[i],
+            other.deduped.representatives[j],
+        );
+        match (&self.vectors, &other.vectors, &self.traces, &other.traces) {
+            (Some(va), Some(vb), _, _) => {
+                Distance::clamped(1.0 - crate::tokens::sparse_cosine(&va[i], &vb[j]))
+            }
+            (_, _, Some(ta), Some(tb)) => {
+                stacktrace::trace_distance(a.as_ref(), &ta[i], b.as_ref(), &tb[j])
+            }
+            _ => (self.config.compare)(a, b),
+        }
+    }
+
+    /// Cluster the representatives under the configured metric, threshold
+    /// and blocking.
+    fn clustered(&self) -> Clustered<'a, V> {
+        let representatives = &self.deduped.representatives;
+        if representatives.len() <= 1 {
+            return Clustered {
+                matched: vec![representatives.clone()],
+                unmatched: vec![],
+            };
+        }
+
+        let config = self.config;
+        let indices: Vec<usize> = (0..representatives.len()).collect();
+        let distance = |a: &usize, b: &usize| self.distance(*a, *b);
+        let clusters = match self.candidates() {
+            None => cluster(&indices, distance, config.threshold.clone(), config.method),
             Some(c) => cluster_with_candidates(
-                &deduped.representatives,
-                c,
+                &indices,
+                &c,
                 distance,
                 config.threshold.clone(),
                 config.method,
             ),
-        }
-    };
-    let clustered = resolve_string_clusters(clusters, &deduped.representatives);
+        };
+        resolve_string_clusters(clusters, representatives)
+    }
 
-    expand(clustered, &deduped.duplicates, normalize)
+    fn candidates(&self) -> Option<Vec<(usize, usize)>> {
+        candidate_pairs(
+            &self.deduped.representatives,
+            &self.deduped.keys,
+            self.config,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{group_similar, Config};
+    use crate::{group_similar, Blocking, Config, Distance};
     use std::collections::BTreeMap;
     use std::convert::TryInto;
 
@@ -811,6 +1156,21 @@ mod tests {
         seen.len() == refs.len()
     }
 
+    #[quickcheck]
+    fn prop_collapsed_counts_match_expanded_clusters(values: Vec<String>) -> bool {
+        let refs: Vec<&str> = values.iter().map(|s| s.as_str()).collect();
+        let config: Config<&str> = Config::jaro_winkler(0.25_f64.try_into().unwrap())
+            .with_normalizer(crate::normalize::default_normalizer());
+        let expanded = group_similar(&refs, &config);
+        let collapsed = crate::group_similar_collapsed(&refs, &config);
+
+        expanded.len() == collapsed.len()
+            && expanded.iter().all(|(k, vs)| {
+                let counted = &collapsed[k];
+                counted[0].0 == *k && counted.iter().map(|(_, n)| n).sum::<usize>() == 1 + vs.len()
+            })
+    }
+
     #[quickcheck]
     fn prop_empty_threshold_produces_singletons(values: Vec<String>) -> bool {
         if values.is_empty() {
@@ -900,6 +1260,86 @@ mod tests {
         assert_eq!(result.unmatched, vec![0]);
     }
 
+    #[test]
+    fn layer1_nearest_neighbors_dense_and_candidates_agree() {
+        use crate::{nearest_neighbors, Distance};
+
+        let items = vec![0.0_f32, 0.05, 0.1, 0.9, 0.95];
+        let distance = |a: &f32, b: &f32| Distance::clamped((a - b).abs());
+
+        let dense = nearest_neighbors(&items, 2, distance, None);
+        let nearest: Vec<Vec<usize>> = dense
+            .iter()
+            .map(|row| row.iter().map(|(j, _)| *j).collect())
+            .collect();
+        assert_eq!(nearest[0], vec![1, 2]);
+        assert_eq!(nearest[3], vec![4, 2]);
+
+        let candidates = vec![(0, 1), (0, 2), (1, 2), (3, 4)];
+        let blocked = nearest_neighbors(&items, 2, distance, Some(&candidates));
+        assert_eq!(blocked[0], dense[0]);
+        assert_eq!(blocked[3].len(), 1);
+    }
+
+    #[test]
+    fn similar_neighbors_collapses_duplicates() {
+        let values = vec!["Jane", "June", "Jane", "Joseph"];
+        let config = Config::jaro_winkler(0.25_f64.try_into().unwrap());
+        let neighbors = crate::similar_neighbors(&values, &config, 1);
+
+        assert_eq!(neighbors.len(), 3);
+        assert_eq!(neighbors[&"Jane"][0].0, &"June");
+    }
+
+    #[test]
+    fn similar_pairs_index_representatives_and_respect_max_distance() {
+        let values = vec!["Jane", "June", "Jane", "Joseph"];
+        let config = Config::jaro_winkler(0.25_f64.try_into().unwrap());
+
+        let all = crate::similar_pairs(&values, &config, None);
+        assert_eq!(
+            all.iter().map(|&(i, j, _)| (i, j)).collect::<Vec<_>>(),
+            vec![(0, 1), (0, 3), (1, 3)]
+        );
+
+        let max = 0.2_f64.try_into().unwrap();
+        let close = crate::similar_pairs(&values, &config, Some(&max));
+        assert_eq!(close.len(), 1);
+        assert_eq!((close[0].0, close[0].1), (0, 1));
+    }
+
+    #[test]
+    fn link_only_matches_across_sides() {
+        let left = vec!["Target T-1244", "Blue Bottle 0042", "xyzzy 99", "TARGET"];
+        let right = vec!["Target", "Blue Bottle Coffee", "Target.com"];
+        let config = || {
+            Config::jaro_winkler(0.35_f64.try_into().unwrap())
+                .with_normalizer(|s: &str| s.to_lowercase())
+        };
+
+        for config in [config().without_blocking(), config().with_blocking(0.0)] {
+            let links = crate::link(&left, &right, &config, 1);
+            assert_eq!(links.len(), 4);
+            assert_eq!(links[0][0].0, 0);
+            assert_eq!(links[1][0].0, 1);
+            assert!(links[2].is_empty());
+            assert_eq!(links[3], vec![(0, Distance::MIN)]);
+        }
+    }
+
+    #[test]
+    fn link_sorted_neighborhood_windows_hold_the_other_side() {
+        // Sorted together, the left records would fill every window of 2
+        // but the last one before "order 5".
+        let left = vec!["order 1", "order 2", "order 3", "order 4"];
+        let right = vec!["order 5"];
+        let config = Config::jaro_winkler(0.2_f64.try_into().unwrap())
+            .with_blocking_strategy(Blocking::SortedNeighborhood { window: 2 });
+
+        let links = crate::link(&left, &right, &config, 1);
+        assert!(links.iter().all(|row| row.len() == 1 && row[0].0 == 0));
+    }
+
     #[test]
     fn config_with_blocking_dispatches_through_group_similar() {
         // Both paths should produce identical partitions on this clearly
@@ -3,17 +3,29 @@
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 use colored::*;
-use group_similar::{group_similar, normalize, Config, Threshold};
+use group_similar::{
+    blocking_report, cluster_template, decompress, diff_results, estimate_recall, find_timestamp,
+    format_timestamp, group_similar, group_similar_collapsed, group_similar_weighted, link,
+    normalize, open_input, read_records_with, similar_neighbors, similar_pairs, total_weight,
+    Assignment, Blocking, Change, Config, Counted, Distance, Framing, Leaders, QGramOptions,
+    Separator, Shingle, SimilarityIndex, Sourced, Threshold, Timeline, Weighted,
+};
 use std::collections::{BTreeMap, HashMap};
-use std::io::{self, Read};
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 use structopt::StructOpt;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Metric {
     Jaro,
+    Levenshtein,
     Cosine,
     CosinePos,
+    StackTrace,
 }
 
 impl FromStr for Metric {
@@ -21,10 +33,106 @@ impl FromStr for Metric {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "jaro" => Ok(Metric::Jaro),
+            "levenshtein" => Ok(Metric::Levenshtein),
             "cosine" => Ok(Metric::Cosine),
             "cosine-pos" => Ok(Metric::CosinePos),
+            "stacktrace" => Ok(Metric::StackTrace),
             other => Err(format!(
-                "metric must be one of: jaro, cosine, cosine-pos; got '{}'",
+                "metric must be one of: jaro, levenshtein, cosine, cosine-pos, stacktrace; got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+/// Width of a --timeline bucket, e.g. `30s`, `15m`, `1h` or `1d`.
+#[derive(Debug, Clone, Copy)]
+pub struct Bucket(i64);
+
+impl FromStr for Bucket {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let unit = match &s[split..] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            "d" => 86_400,
+            _ => 0,
+        };
+        match s[..split].parse::<i64>() {
+            Ok(n) if n > 0 && unit > 0 => n
+                .checked_mul(unit)
+                .map(Bucket)
+                .ok_or_else(|| format!("bucket '{}' is too long", s)),
+            _ => Err(format!(
+                "bucket must be a positive number followed by s, m, h or d; got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BlockingKind {
+    QGram,
+    Token,
+    Sorted,
+    Prefix,
+    MinHash,
+}
+
+impl FromStr for BlockingKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qgram" => Ok(BlockingKind::QGram),
+            "token" => Ok(BlockingKind::Token),
+            "sorted" => Ok(BlockingKind::Sorted),
+            "prefix" => Ok(BlockingKind::Prefix),
+            "minhash" => Ok(BlockingKind::MinHash),
+            other => Err(format!(
+                "blocking must be one of: qgram, token, sorted, prefix, minhash; got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ShingleKind {
+    QGram,
+    Token,
+}
+
+impl FromStr for ShingleKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qgram" => Ok(ShingleKind::QGram),
+            "token" => Ok(ShingleKind::Token),
+            other => Err(format!(
+                "shingle must be one of: qgram, token; got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Algorithm {
+    Hierarchical,
+    Leader,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hierarchical" => Ok(Algorithm::Hierarchical),
+            "leader" => Ok(Algorithm::Leader),
+            other => Err(format!(
+                "algorithm must be one of: hierarchical, leader; got '{}'",
                 other
             )),
         }
@@ -41,7 +149,7 @@ pub struct Flags {
     /// Threshold (0-1) controlling how strict matching is. The right value
     /// depends on the chosen metric: cosine-pos ~0.3-0.4 (default), cosine
     /// ~0.5, jaro ~0.1.
-    #[structopt(long, default_value = "0.3")]
+    #[structopt(long, default_value = "0.3", global = true)]
     pub threshold: Threshold,
 
     /// Include singletons (records that didn't cluster with anything else) in
@@ -49,63 +157,1327 @@ pub struct Flags {
     #[structopt(long)]
     pub all: bool,
 
-    /// Render results in JSON format
+    /// Print one line per cluster: its size and the normalized template
+    /// shared by its members, with `<*>` where they differ.
     #[structopt(long)]
+    pub templates: bool,
+
+    /// Summarize each cluster instead of listing its members: its size and
+    /// the distinct values each built-in pattern replaced (which ids,
+    /// timestamps, URLs, ...), whatever the --normalize setting.
+    #[structopt(long, conflicts_with = "templates")]
+    pub stats: bool,
+
+    /// Under each cluster header, show the N most frequent values replaced
+    /// at each placeholder position (and the range of timestamps). With
+    /// --json, print one object per cluster.
+    #[structopt(long = "top-values", conflicts_with_all = &["templates", "stats"])]
+    pub top_values: Option<usize>,
+
+    /// Show when each cluster's records happened, newest cluster first:
+    /// first and last seen and a per-bucket histogram, from the first
+    /// ISO-8601 timestamp in each record. With --json, one object per cluster.
+    #[structopt(long, conflicts_with_all = &["templates", "stats", "top-values"])]
+    pub timeline: bool,
+
+    /// Bucket width for --timeline: 30s, 15m, 1h, 1d, ...
+    #[structopt(long, default_value = "1h")]
+    pub bucket: Bucket,
+
+    /// Render results in JSON format
+    #[structopt(long, global = true)]
     pub json: bool,
 
     /// Disable normalization. By default, embedded IDs, hex addresses, bare
-    /// hex tokens, and quoted timestamps are collapsed to placeholders before
-    /// deduplication.
-    #[structopt(long = "no-normalize")]
+    /// hex tokens, UUIDs, timestamps, IP and email addresses, URLs, paths and
+    /// durations are collapsed to placeholders before deduplication.
+    #[structopt(long = "no-normalize", global = true)]
     pub no_normalize: bool,
 
-    /// Similarity metric: jaro (Jaro-Winkler), cosine (IDF-weighted token
-    /// cosine), or cosine-pos (cosine with leading-token position boost,
-    /// default).
-    #[structopt(long, default_value = "cosine-pos")]
-    pub metric: Metric,
+    /// TOML file of regex normalization rules, applied before (or, with
+    /// `defaults = false`, instead of) the built-in patterns. Each
+    /// `[[rule]]` has a `name`, `pattern`, `replacement` and optional
+    /// `priority`.
+    #[structopt(
+        long = "normalize-rules",
+        parse(from_os_str),
+        conflicts_with = "no-normalize",
+        global = true
+    )]
+    pub normalize_rules: Option<PathBuf>,
+
+    /// Comma-separated normalizers to apply instead of the default: pattern
+    /// groups (url, email, path, hex, uuid, timestamp, ip, duration,
+    /// numeric), transforms (lowercase, whitespace, punctuation, diacritics,
+    /// nfkc, fullwidth) or presets (default, logs, entity, merchants,
+    /// stacktraces, none).
+    #[structopt(
+        long,
+        conflicts_with_all = &["no-normalize", "normalize-rules"],
+        global = true
+    )]
+    pub normalize: Option<String>,
+
+    /// Replace values with placeholders encoding their shape (`<num:6>`,
+    /// `<hex:16>`, `<ts>`) instead of fixed ones, so ids of different
+    /// lengths no longer share a key.
+    #[structopt(
+        long = "typed-placeholders",
+        conflicts_with_all = &["no-normalize", "normalize-rules"],
+        global = true
+    )]
+    pub typed_placeholders: bool,
+
+    /// With --typed-placeholders, keep up to N leading characters of each
+    /// replaced value (`<num:6:10>`).
+    #[structopt(long = "keep-prefix", requires = "typed-placeholders", global = true)]
+    pub keep_prefix: Option<usize>,
+
+    /// Instead of clustering, print each input line's normalized key (with
+    /// --json, the record and its key).
+    #[structopt(long = "show-normalized")]
+    pub show_normalized: bool,
+
+    /// Similarity metric: jaro (Jaro-Winkler; the default for --algorithm
+    /// leader), levenshtein (normalized edit distance), cosine (IDF-weighted
+    /// token cosine), cosine-pos (cosine with leading-token position boost,
+    /// the default otherwise), or stacktrace (overlap
+    /// of Java, Ruby and Python stack frames; pair with --continuation or
+    /// --record-separator).
+    /// With levenshtein, q-gram blocking is lossless and --tau is ignored.
+    #[structopt(long, global = true)]
+    pub metric: Option<Metric>,
 
     /// Disable q-gram blocking. By default, blocking is on (candidate
     /// filtering + per-component complete-link clustering, with the same or
-    /// near-identical output partition).
-    #[structopt(long = "no-blocked")]
+    /// near-identical output partition — use --check-blocking to measure).
+    #[structopt(long = "no-blocked", global = true)]
     pub no_blocked: bool,
 
     /// Tuning parameter for the q-gram blocking filter. Higher values reject
     /// more candidate pairs (faster but risks dropping real matches). Ignored
     /// when --no-blocked is set. Typical range: 0.1-0.4.
-    #[structopt(long, default_value = "0.3")]
+    #[structopt(long, default_value = "0.3", global = true)]
     pub tau: f64,
+
+    /// Gram length in characters (2-5), for --blocking qgram.
+    #[structopt(long = "qgram-size", default_value = "3", global = true)]
+    pub qgram_size: usize,
+
+    /// Pad strings with begin/end markers before extracting grams, for
+    /// --blocking qgram.
+    #[structopt(long = "qgram-pad", global = true)]
+    pub qgram_pad: bool,
+
+    /// Lowercase strings before extracting grams, for --blocking qgram.
+    #[structopt(long = "qgram-case-fold", global = true)]
+    pub qgram_case_fold: bool,
+
+    /// Candidate-pair strategy when blocking is on: qgram (shared character
+    /// trigrams, default), token (shared rare tokens), sorted (neighbors in
+    /// sorted order), prefix (same leading tokens), or minhash (MinHash-LSH
+    /// buckets over --shingle sets; near-linear on large inputs). The
+    /// stacktrace metric defaults to pairing traces with the same innermost
+    /// frame.
+    #[structopt(long, global = true)]
+    pub blocking: Option<BlockingKind>,
+
+    /// Largest fraction of records a token may appear in and still count as
+    /// rare, for --blocking token.
+    #[structopt(long = "max-df", default_value = "0.05", global = true)]
+    pub max_df: f64,
+
+    /// Number of consecutive sorted records compared together, for
+    /// --blocking sorted.
+    #[structopt(long, default_value = "20", global = true)]
+    pub window: usize,
+
+    /// Number of leading tokens in the block key, for --blocking prefix.
+    #[structopt(long = "prefix-tokens", default_value = "2", global = true)]
+    pub prefix_tokens: usize,
+
+    /// Records with fewer distinct grams than this pair with everything, for
+    /// --blocking qgram. 0 makes short records need a gram in common;
+    /// --qgram-pad then keeps short transposed records together.
+    #[structopt(long = "qgram-min", default_value = "15", global = true)]
+    pub qgram_min: u32,
+
+    /// Grams present in more than this fraction of records are ignored, for
+    /// --blocking qgram.
+    #[structopt(long = "qgram-common", default_value = "0.8", global = true)]
+    pub qgram_common: f64,
+
+    /// Before clustering, estimate blocking recall on a sample of this many
+    /// deduplicated records and print how many true matches were lost to
+    /// stderr.
+    #[structopt(long)]
+    pub recall: Option<usize>,
+
+    /// Instead of clustering, print each distinct record with its K nearest
+    /// records and their distances.
+    #[structopt(long, conflicts_with_all = &["pairs", "check-blocking"])]
+    pub neighbors: Option<usize>,
+
+    /// Instead of clustering, print every scored candidate pair of distinct
+    /// records as `i,j,distance` CSV (0-based line numbers), or NDJSON with
+    /// --json.
+    #[structopt(long, conflicts_with = "check-blocking")]
+    pub pairs: bool,
+
+    /// With --pairs, only print pairs at most this far apart.
+    #[structopt(long = "max-distance")]
+    pub max_distance: Option<Threshold>,
+
+    /// Instead of clustering, compare the blocked and dense pipelines on a
+    /// sample and report candidate-pair savings, pair recall, adjusted Rand
+    /// index and (for --blocking qgram) a suggested --tau.
+    #[structopt(long = "check-blocking")]
+    pub check_blocking: bool,
+
+    /// Number of deduplicated records sampled by --check-blocking.
+    #[structopt(long, default_value = "1000")]
+    pub sample: usize,
+
+    /// Pair recall the --tau suggested by --check-blocking must reach.
+    #[structopt(long = "target-recall", default_value = "0.99")]
+    pub target_recall: f64,
+
+    /// Number of LSH bands, for --blocking minhash. More bands catch
+    /// less-similar pairs.
+    #[structopt(long, default_value = "20", global = true)]
+    pub bands: NonZeroUsize,
+
+    /// Hashes per LSH band, for --blocking minhash. More rows make each band
+    /// stricter.
+    #[structopt(long, default_value = "5", global = true)]
+    pub rows: NonZeroUsize,
+
+    /// Shingles hashed by --blocking minhash: qgram (overlapping character
+    /// n-grams of --shingle-size, default) or token (whole tokens, better
+    /// for long records that differ in word order).
+    #[structopt(long, default_value = "qgram", global = true)]
+    pub shingle: ShingleKind,
+
+    /// Character n-gram length for --shingle qgram.
+    #[structopt(long = "shingle-size", default_value = "3", global = true)]
+    pub shingle_size: usize,
+
+    /// Clustering algorithm: hierarchical (complete-link over the whole
+    /// input, default) or leader (single pass over the input with bounded
+    /// memory; each record is printed with its cluster id as soon as it is
+    /// read). Leader compares with jaro and rejects any other --metric, since
+    /// the cosine metrics are fitted to the full corpus; leaders are indexed
+    /// by q-gram using --tau and the --qgram-* options.
+    #[structopt(long, default_value = "hierarchical")]
+    pub algorithm: Algorithm,
+
+    /// List each distinct normalized record once per cluster, as `×N` and
+    /// one example, instead of every duplicate.
+    #[structopt(
+        long,
+        conflicts_with_all = &["templates", "stats", "top-values", "timeline", "count-column"]
+    )]
+    pub collapse: bool,
+
+    /// List at most N members per cluster, then how many more there are.
+    /// Applies to text output.
+    #[structopt(long = "max-members")]
+    pub max_members: Option<usize>,
+
+    /// Read pre-aggregated input: each line is tab-separated and this
+    /// 1-based column holds how many times the rest of the line occurred.
+    /// Cluster sizes are summed counts, and each cluster is headed by its
+    /// most frequent member. Always clusters hierarchically.
+    #[structopt(
+        long = "count-column",
+        conflicts_with_all = &[
+            "templates", "stats", "top-values", "timeline", "neighbors", "pairs",
+            "check-blocking", "recall", "show-normalized", "algorithm",
+        ]
+    )]
+    pub count_column: Option<usize>,
+
+    /// Remember each record's file and line: print them beside each record,
+    /// and with --json print one object per cluster that includes them.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "templates", "stats", "top-values", "timeline", "neighbors", "pairs",
+            "check-blocking", "recall", "show-normalized", "collapse", "count-column",
+        ]
+    )]
+    pub source: bool,
+
+    /// What separates records: line (default), blank (blank lines), nul, or
+    /// a regex matching the line each record starts with, such as
+    /// '^\d{4}-\d\d-\d\d' for timestamped logs. Also frames the query,
+    /// corpus and link files.
+    #[structopt(long = "record-separator", default_value = "line", global = true)]
+    pub record_separator: Separator,
+
+    /// Lines starting with whitespace or `at ` (stack frames, wrapped
+    /// messages) continue the record before them, as does the exception line
+    /// ending a Python traceback.
+    #[structopt(long, global = true)]
+    pub continuation: bool,
+
+    /// Files to read records from, instead of stdin. Quoted globs such as
+    /// 'logs/*.gz' are expanded; gzip and zstd input is decompressed.
+    #[structopt(parse(from_os_str))]
+    pub files: Vec<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Flags {
+    /// The --metric, or the algorithm's default: jaro for leader, cosine-pos
+    /// otherwise.
+    pub fn metric(&self) -> Metric {
+        match (self.metric, &self.algorithm) {
+            (Some(metric), _) => metric,
+            (None, Algorithm::Leader) => Metric::Jaro,
+            (None, _) => Metric::CosinePos,
+        }
+    }
+
+    /// The --blocking strategy, or none to keep the metric's own default:
+    /// the stacktrace metric blocks on frames, the rest on q-grams.
+    pub fn blocking(&self) -> Option<BlockingKind> {
+        match (self.blocking, self.metric()) {
+            (Some(blocking), _) => Some(blocking),
+            (None, Metric::StackTrace) => None,
+            (None, _) => Some(BlockingKind::QGram),
+        }
+    }
 }
 
-fn read_from_stdin() -> io::Result<String> {
-    let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer)?;
-    Ok(buffer)
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Match each record of stdin against an indexed corpus and print the
+    /// corpus records within --threshold, closest first. The metric,
+    /// normalizer and q-gram blocking options apply as for clustering.
+    Query {
+        /// File of corpus records.
+        #[structopt(long, parse(from_os_str))]
+        corpus: PathBuf,
+
+        /// Maximum number of matches printed per query.
+        #[structopt(short, long, default_value = "5")]
+        k: usize,
+    },
+    /// Link each record of --left to its closest records in --right within
+    /// --threshold. Blocking only generates cross pairs: each block or
+    /// --blocking sorted window pairs left records with right ones alone.
+    Link {
+        /// File of records to be matched.
+        #[structopt(long, parse(from_os_str))]
+        left: PathBuf,
+
+        /// File of reference records.
+        #[structopt(long, parse(from_os_str))]
+        right: PathBuf,
+
+        /// Maximum number of matches printed per left record.
+        #[structopt(short, long, default_value = "1")]
+        k: usize,
+    },
+    /// Compare two runs saved with --json (alone or with --collapse, --stats,
+    /// --top-values, --source or --count-column): match their clusters by
+    /// representative within --threshold and report the ones that are new,
+    /// gone, grew or shrank. Save both runs with --all: plain --json leaves
+    /// out single-record clusters, so a cluster that shrank to one record
+    /// reads as gone.
+    Diff {
+        /// Results of the earlier run.
+        #[structopt(long, parse(from_os_str))]
+        before: PathBuf,
+
+        /// Results of the later run.
+        #[structopt(long, parse(from_os_str))]
+        after: PathBuf,
+
+        /// Size change, in percent, below which a cluster is unchanged.
+        #[structopt(long, default_value = "20")]
+        change: f64,
+    },
 }
 
-fn main() -> io::Result<()> {
-    let flags = Flags::from_args();
-    let stdin = read_from_stdin()?;
-    let input = stdin.lines().collect::<Vec<_>>();
+/// The positional files, with globs expanded in sorted order.
+fn input_paths(flags: &Flags) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for file in &flags.files {
+        let pattern = file.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push(file.clone());
+            continue;
+        }
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: {}", pattern, message),
+            )
+        };
+        let matched = glob::glob(&pattern)
+            .map_err(|e| invalid(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| invalid(e.to_string()))?;
+        if matched.is_empty() {
+            return Err(invalid("no files match".to_string()));
+        }
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// How --record-separator and --continuation split input into records.
+fn framing(flags: &Flags) -> Framing {
+    Framing {
+        separator: flags.record_separator.clone(),
+        continuation: flags.continuation,
+    }
+}
+
+/// Records of every input file in turn, or of stdin when there are none.
+fn input_records(
+    flags: &Flags,
+) -> io::Result<Box<dyn Iterator<Item = io::Result<Sourced<String>>>>> {
+    let paths = input_paths(flags)?;
+    let framing = framing(flags);
+    if paths.is_empty() {
+        let stdin = decompress(io::stdin().lock())?;
+        return Ok(Box::new(read_records_with(stdin, Path::new("-"), framing)));
+    }
+
+    Ok(Box::new(paths.into_iter().flat_map(
+        move |path| -> Box<dyn Iterator<Item = io::Result<Sourced<String>>>> {
+            match open_input(&path) {
+                Ok(reader) => Box::new(read_records_with(reader, &path, framing.clone())),
+                Err(e) => Box::new(std::iter::once(Err(io::Error::new(
+                    e.kind(),
+                    format!("{}: {}", path.display(), e),
+                )))),
+            }
+        },
+    )))
+}
+
+/// `file:line` of a record, for --source.
+fn location<V>(record: &Sourced<V>) -> String {
+    format!("{}:{}", record.source.display(), record.line)
+}
+
+/// `record` with its continuation lines indented one level past `depth`,
+/// the indent of its first line, so multi-line records stay distinct.
+fn continued(record: &str, depth: usize) -> String {
+    record.replace('\n', &format!("\n{:1$}", "", depth + 3))
+}
+
+/// Print a cluster's member lines, cut off at `--max-members`.
+fn print_members<T: std::fmt::Display>(flags: &Flags, members: &[T]) {
+    let shown = flags
+        .max_members
+        .unwrap_or(members.len())
+        .min(members.len());
+    for member in &members[..shown] {
+        println!("   {}", continued(&member.to_string(), 3));
+    }
+    if shown < members.len() {
+        println!(
+            "   {}",
+            format!("… and {} more", members.len() - shown).dimmed()
+        );
+    }
+}
+
+/// Print `--top-values` lines: the `n` most frequent values per placeholder
+/// position, or the range for timestamps.
+fn print_top_values(histograms: &[normalize::ValueHistogram], n: usize) {
+    for h in histograms {
+        let name = if h.label.is_empty() {
+            format!("#{} ({})", h.position, h.pattern)
+        } else {
+            format!("{} ({})", h.label, h.pattern)
+        };
+        let summary = match h.range() {
+            Some((first, last)) if h.pattern.ends_with("timestamps") => {
+                format!("{} → {}", first, last)
+            }
+            _ => {
+                let top: Vec<String> = h
+                    .counts
+                    .iter()
+                    .take(n)
+                    .map(|(value, count)| format!("{} ×{}", value, count))
+                    .collect();
+                let more = if h.counts.len() > n { ", …" } else { "" };
+                format!("{}{}", top.join(", "), more)
+            }
+        };
+        println!(
+            "   {}: {} {}",
+            name.cyan(),
+            summary,
+            format!("({} distinct)", h.counts.len()).dimmed()
+        );
+    }
+}
+
+/// Columns in a --timeline histogram; finer buckets are merged to fit.
+const TIMELINE_WIDTH: usize = 60;
+
+fn print_timelines(flags: &Flags, results: &BTreeMap<&&str, Vec<&&str>>) {
+    let width = flags.bucket.0;
+    let mut timelines: Vec<(&str, usize, Timeline)> = results
+        .iter()
+        .filter(|(_, v)| flags.all || !v.is_empty())
+        .map(|(k, vs)| {
+            let members: Vec<&str> = std::iter::once(**k).chain(vs.iter().map(|v| **v)).collect();
+            let timeline = Timeline::new(&members, |r| find_timestamp(r), width);
+            (**k, members.len(), timeline)
+        })
+        .collect();
+    // Newest families first; clusters without timestamps last.
+    timelines.sort_by_key(|(_, _, t)| std::cmp::Reverse(t.first_seen));
 
-    let mut config: Config<&str> = match flags.metric {
-        Metric::Jaro => Config::jaro_winkler(flags.threshold.clone()),
-        Metric::Cosine => Config::token_cosine(&input, flags.threshold.clone()),
-        Metric::CosinePos => Config::token_cosine_positional(&input, flags.threshold.clone()),
+    if flags.json {
+        for (representative, size, t) in &timelines {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "representative": representative,
+                    "size": size,
+                    "first_seen": t.first_seen.map(format_timestamp),
+                    "last_seen": t.last_seen.map(format_timestamp),
+                    "undated": t.undated,
+                    "buckets": t
+                        .buckets
+                        .iter()
+                        .map(|(start, count)| (format_timestamp(*start), count))
+                        .collect::<Vec<_>>(),
+                })
+            );
+        }
+        return;
+    }
+
+    // Every histogram spans the same range so clusters line up.
+    let starts = timelines
+        .iter()
+        .flat_map(|(_, _, t)| t.buckets.iter().map(|b| b.0));
+    let (min, max) = match (starts.clone().min(), starts.max()) {
+        (Some(min), Some(max)) => (min, max),
+        _ => (0, 0),
     };
-    if !flags.no_normalize {
-        config = config.with_normalizer(normalize::default_normalizer());
+    let span = ((max - min) / width + 1) as usize;
+    let columns = span.min(TIMELINE_WIDTH);
+
+    for (representative, size, t) in &timelines {
+        println!("{}", continued(representative, 0).green().bold());
+        match (t.first_seen, t.last_seen) {
+            (Some(first), Some(last)) => println!(
+                "   {} records, first seen {}, last seen {}",
+                size,
+                format_timestamp(first).cyan(),
+                format_timestamp(last).cyan()
+            ),
+            _ => println!("   {} records, no timestamps", size),
+        }
+        if !t.buckets.is_empty() {
+            let mut counts = vec![0; columns];
+            for (start, count) in &t.buckets {
+                counts[((start - min) / width) as usize * columns / span] += count;
+            }
+            println!("   {}", sparkline(&counts).dimmed());
+        }
+        println!();
+    }
+}
+
+/// One block character per count, scaled to the largest.
+fn sparkline(counts: &[usize]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&c| match c {
+            0 => ' ',
+            c => BLOCKS[(c * BLOCKS.len() - 1) / max],
+        })
+        .collect()
+}
+
+/// Distinct values listed per pattern by --stats before eliding the rest.
+const STATS_VALUES: usize = 10;
+
+fn normalizer(flags: &Flags) -> io::Result<normalize::Normalizer> {
+    if flags.no_normalize {
+        return Ok(Box::new(normalize::identity));
     }
-    config = if flags.no_blocked {
-        config.without_blocking()
+    match &flags.normalize_rules {
+        Some(path) => {
+            let rules = normalize::Rules::from_toml(&fs::read_to_string(path)?).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })?;
+            Ok(normalize::rules_normalizer(rules))
+        }
+        None => {
+            let placeholders = if flags.typed_placeholders {
+                normalize::Placeholders::Typed {
+                    keep_prefix: flags.keep_prefix.unwrap_or(0),
+                }
+            } else {
+                normalize::Placeholders::Fixed
+            };
+            match &flags.normalize {
+                Some(spec) => normalize::named_with(spec, placeholders)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
+                None => Ok(normalize::default_normalizer_with(placeholders)),
+            }
+        }
+    }
+}
+
+fn build_config<V: AsRef<str>>(flags: &Flags, corpus: &[V]) -> io::Result<Config<V>> {
+    configure(
+        flags,
+        match flags.metric() {
+            Metric::Jaro => Config::jaro_winkler(flags.threshold.clone()),
+            Metric::Levenshtein => Config::levenshtein(flags.threshold.clone()),
+            Metric::StackTrace => Config::stack_trace(flags.threshold.clone()),
+            Metric::Cosine => Config::token_cosine(corpus, flags.threshold.clone()),
+            Metric::CosinePos => Config::token_cosine_positional(corpus, flags.threshold.clone()),
+        },
+    )
+}
+
+/// [`build_config`] with IDF weighted by each record's count.
+fn build_weighted_config<V: AsRef<str> + Weighted>(
+    flags: &Flags,
+    corpus: &[V],
+) -> io::Result<Config<V>> {
+    configure(
+        flags,
+        match flags.metric() {
+            Metric::Jaro => Config::jaro_winkler(flags.threshold.clone()),
+            Metric::Levenshtein => Config::levenshtein(flags.threshold.clone()),
+            Metric::StackTrace => Config::stack_trace(flags.threshold.clone()),
+            Metric::Cosine => Config::token_cosine_weighted(corpus, flags.threshold.clone()),
+            Metric::CosinePos => {
+                Config::token_cosine_positional_weighted(corpus, flags.threshold.clone())
+            }
+        },
+    )
+}
+
+fn qgram_options(flags: &Flags) -> QGramOptions {
+    QGramOptions {
+        q: flags.qgram_size,
+        pad: flags.qgram_pad,
+        case_fold: flags.qgram_case_fold,
+        min_grams: flags.qgram_min,
+        common_cutoff: flags.qgram_common,
+    }
+}
+
+/// Apply the normalizer and blocking flags to a metric's config.
+fn configure<V: AsRef<str>>(flags: &Flags, mut config: Config<V>) -> io::Result<Config<V>> {
+    config = config.with_normalizer(normalizer(flags)?);
+    config = match flags.blocking() {
+        _ if flags.no_blocked => config.without_blocking(),
+        None => config,
+        Some(blocking) => config.with_blocking_strategy(match blocking {
+            BlockingKind::QGram => Blocking::QGram {
+                tau: flags.tau,
+                options: qgram_options(flags),
+            },
+            BlockingKind::Token => Blocking::Token {
+                max_df: flags.max_df,
+            },
+            BlockingKind::Sorted => Blocking::SortedNeighborhood {
+                window: flags.window,
+            },
+            BlockingKind::Prefix => Blocking::Prefix {
+                tokens: flags.prefix_tokens,
+            },
+            BlockingKind::MinHash => Blocking::MinHash {
+                bands: flags.bands.get(),
+                rows: flags.rows.get(),
+                shingle: match flags.shingle {
+                    ShingleKind::QGram => Shingle::QGram(flags.shingle_size),
+                    ShingleKind::Token => Shingle::Token,
+                },
+            },
+        }),
+    };
+    Ok(config)
+}
+
+/// Records of `path`, framed like the clustering input.
+fn read_framed(flags: &Flags, path: &Path) -> io::Result<Vec<String>> {
+    read_records_with(open_input(path)?, path, framing(flags))
+        .map(|record| record.map(|r| r.value))
+        .collect()
+}
+
+fn print_matches(flags: &Flags, record: &str, matches: &[(usize, Distance)], targets: &[String]) {
+    if flags.json {
+        let matches: Vec<_> = matches
+            .iter()
+            .map(|(i, d)| {
+                serde_json::json!({
+                    "index": i,
+                    "record": targets[*i],
+                    "distance": d.value(),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({ "query": record, "matches": matches })
+        );
     } else {
-        config.with_blocking(flags.tau)
+        println!("{}", continued(record, 0).green().bold());
+        for (i, d) in matches {
+            println!(
+                "   {:.3}  {}",
+                d.value(),
+                continued(&targets[*i], 3).dimmed().italic()
+            );
+        }
+        println!();
+    }
+}
+
+fn run_query(flags: &Flags, corpus: &Path, k: usize) -> io::Result<()> {
+    let corpus = read_framed(flags, corpus)?;
+    let index = SimilarityIndex::new(&corpus, build_config(flags, &corpus)?);
+
+    for record in read_records_with(io::stdin().lock(), Path::new("-"), framing(flags)) {
+        let record = record?.value;
+        print_matches(flags, &record, &index.query(&record, k), &corpus);
+    }
+
+    Ok(())
+}
+
+fn run_link(flags: &Flags, left: &Path, right: &Path, k: usize) -> io::Result<()> {
+    let left = read_framed(flags, left)?;
+    let right = read_framed(flags, right)?;
+    let both: Vec<String> = left.iter().chain(&right).cloned().collect();
+    let config = build_config(flags, &both)?;
+
+    for (record, matches) in left.iter().zip(link(&left, &right, &config, k)) {
+        print_matches(flags, record, &matches, &right);
+    }
+
+    Ok(())
+}
+
+/// `(representative, size)` per cluster of a saved run: either the single
+/// object `--json` prints, with or without `--collapse`, or the per-cluster
+/// lines of `--stats`, `--top-values`, `--source` or `--count-column` with
+/// `--json`.
+fn read_run(path: &Path) -> io::Result<Vec<(String, usize)>> {
+    let annotate = |kind, e: &dyn std::fmt::Display| {
+        io::Error::new(kind, format!("{}: {}", path.display(), e))
     };
+    let text = fs::read_to_string(path).map_err(|e| annotate(e.kind(), &e))?;
 
-    let results: BTreeMap<&&str, Vec<&&str>> = group_similar(&input, &config);
+    /// A member as `--json` lists it: the record, or with `--collapse` a
+    /// record and how many records share its key.
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Member {
+        Collapsed { count: usize },
+        Record(serde::de::IgnoredAny),
+    }
+    if let Ok(clusters) = serde_json::from_str::<BTreeMap<String, Vec<Member>>>(&text) {
+        return Ok(clusters
+            .into_iter()
+            .map(|(k, members)| {
+                let size = match members.first() {
+                    // Collapsed clusters count the key's own records too.
+                    Some(Member::Collapsed { .. }) => members
+                        .iter()
+                        .map(|m| match m {
+                            Member::Collapsed { count } => *count,
+                            Member::Record(_) => 1,
+                        })
+                        .sum(),
+                    _ => 1 + members.len(),
+                };
+                (k, size)
+            })
+            .collect());
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Summary {
+        representative: String,
+        size: Option<usize>,
+        #[serde(default)]
+        members: Vec<serde_json::Value>,
+    }
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let summary: Summary =
+                serde_json::from_str(line).map_err(|e| annotate(io::ErrorKind::InvalidData, &e))?;
+            let size = summary.size.unwrap_or(1 + summary.members.len());
+            Ok((summary.representative, size))
+        })
+        .collect()
+}
+
+fn run_diff(flags: &Flags, before: &Path, after: &Path, change: f64) -> io::Result<()> {
+    let before = read_run(before)?;
+    let after = read_run(after)?;
+    let corpus: Vec<String> = before
+        .iter()
+        .chain(&after)
+        .map(|(r, _)| r.clone())
+        .collect();
+    let config = build_config(flags, &corpus)?;
+
+    for c in diff_results(&before, &after, &config, change / 100.0) {
+        if c.change == Change::Unchanged && !flags.all {
+            continue;
+        }
+        let representative = match (c.after, c.before) {
+            (Some(a), _) => &after[a].0,
+            (None, Some(b)) => &before[b].0,
+            (None, None) => unreachable!("every change has a cluster"),
+        };
+        let label = match c.change {
+            Change::New => "new",
+            Change::Gone => "gone",
+            Change::Grew => "grew",
+            Change::Shrank => "shrank",
+            Change::Unchanged => "unchanged",
+        };
+
+        if flags.json {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "change": label,
+                    "representative": representative,
+                    "before": c.before.map(|b| &before[b].0),
+                    "before_size": c.before_size,
+                    "after_size": c.after_size,
+                    "distance": c.distance.map(Distance::value),
+                })
+            );
+        } else {
+            let sizes = format!("{:>6} → {:<6}", c.before_size, c.after_size);
+            let label = match c.change {
+                Change::New | Change::Grew => format!("{:<9}", label).red().bold(),
+                Change::Gone | Change::Shrank => format!("{:<9}", label).green().bold(),
+                Change::Unchanged => format!("{:<9}", label).dimmed(),
+            };
+            println!("{} {} {}", label, sizes, representative);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_show_normalized(flags: &Flags) -> io::Result<()> {
+    let normalizer = normalizer(flags)?;
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+
+    for record in input_records(flags)? {
+        let record = record?;
+        let line = &record.value;
+        let key = normalizer(line);
+        if flags.json {
+            writeln!(
+                out,
+                "{}",
+                serde_json::json!({ "record": line, "normalized": key })
+            )?;
+        } else {
+            writeln!(out, "{}", key)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run_leader(flags: &Flags) -> io::Result<()> {
+    let normalizer = normalizer(flags)?;
+    let config: Config<String> = Config::jaro_winkler(flags.threshold.clone());
+    let mut leaders = Leaders::new(
+        |a: &String, b: &String| config.distance(a, b),
+        flags.threshold.clone(),
+    );
+    if !flags.no_blocked {
+        leaders = leaders.with_qgram_options(flags.tau, qgram_options(flags));
+    }
+
+    for record in input_records(flags)? {
+        let record = record?;
+        let line = &record.value;
+        let assignment = leaders.push(normalizer(line));
+
+        if flags.json {
+            let mut rendered = serde_json::json!({
+                "cluster": assignment.cluster(),
+                "leader": matches!(assignment, Assignment::Leader(_)),
+                "record": line,
+            });
+            if flags.source {
+                rendered["file"] = serde_json::json!(record.source.display().to_string());
+                rendered["line"] = serde_json::json!(record.line);
+            }
+            println!("{}", rendered);
+        } else {
+            let source = if flags.source {
+                format!("  {}", location(&record).dimmed())
+            } else {
+                String::new()
+            };
+            match assignment {
+                Assignment::Leader(id) => println!(
+                    "{} {}{}",
+                    format!("#{}", id).green(),
+                    continued(line, 0).green().bold(),
+                    source
+                ),
+                Assignment::Member(id) => println!(
+                    "{}    {}{}",
+                    format!("#{}", id).dimmed(),
+                    continued(line, 0).dimmed().italic(),
+                    source
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Split `count<TAB>message` lines on the 1-based count `column`; the
+/// record is the remaining columns.
+fn read_counted(input: &[Sourced<String>], column: usize) -> io::Result<Vec<Counted<String>>> {
+    let invalid = |record: &Sourced<String>, message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", location(record), message),
+        )
+    };
+    if column == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--count-column is 1-based",
+        ));
+    }
+    input
+        .iter()
+        .map(|record| {
+            let mut fields: Vec<&str> = record.value.split('\t').collect();
+            if fields.len() < column {
+                return Err(invalid(record, format!("no column {}", column)));
+            }
+            let count = fields.remove(column - 1);
+            let count = count
+                .trim()
+                .parse()
+                .map_err(|_| invalid(record, format!("bad count {:?}", count)))?;
+            Ok(Counted::new(fields.join("\t"), count))
+        })
+        .collect()
+}
+
+/// `--source` output: clusters with each record's file and line.
+fn run_sourced(flags: &Flags, input: &[Sourced<String>]) -> io::Result<()> {
+    let records: Vec<Sourced<&str>> = input
+        .iter()
+        .map(|r| Sourced {
+            value: r.value.as_str(),
+            source: Arc::clone(&r.source),
+            line: r.line,
+        })
+        .collect();
+    let config = build_config(flags, &records)?;
+    let results = group_similar(&records, &config);
+
+    for (k, vs) in results.iter().filter(|(_, v)| flags.all || !v.is_empty()) {
+        if flags.json {
+            let members: Vec<serde_json::Value> = vs
+                .iter()
+                .map(|v| {
+                    serde_json::json!({
+                        "record": v.value,
+                        "file": v.source.display().to_string(),
+                        "line": v.line,
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "representative": k.value,
+                    "file": k.source.display().to_string(),
+                    "line": k.line,
+                    "size": 1 + vs.len(),
+                    "members": members,
+                })
+            );
+        } else {
+            println!(
+                "{}  {}",
+                continued(k.value, 0).green().bold(),
+                location(k).dimmed()
+            );
+            let lines: Vec<String> = vs
+                .iter()
+                .map(|v| format!("{}  {}", v.value.dimmed().italic(), location(v).dimmed()))
+                .collect();
+            print_members(flags, &lines);
+            println!();
+        }
+    }
+    Ok(())
+}
 
+/// `--collapse` output: each cluster's distinct records with their counts.
+fn print_collapsed(flags: &Flags, results: &BTreeMap<&&str, Vec<(&&str, usize)>>) {
+    let shown = results
+        .iter()
+        .filter(|(_, counted)| flags.all || counted.len() > 1 || counted[0].1 > 1);
     if flags.json {
+        let rendered: HashMap<&str, Vec<serde_json::Value>> = shown
+            .map(|(k, counted)| {
+                let counted = counted
+                    .iter()
+                    .map(|(v, n)| serde_json::json!({ "record": v, "count": n }))
+                    .collect();
+                (**k, counted)
+            })
+            .collect();
+        println!("{}", serde_json::to_string(&rendered).unwrap());
+        return;
+    }
+
+    for (k, counted) in shown {
+        let size: usize = counted.iter().map(|(_, n)| n).sum();
+        println!(
+            "{}  {}",
+            continued(k, 0).green().bold(),
+            format!("×{}", size).green()
+        );
+        let lines: Vec<String> = counted
+            .iter()
+            .map(|(v, n)| format!("{:>7}  {}", format!("×{}", n), v.dimmed().italic()))
+            .collect();
+        print_members(flags, &lines);
+        println!();
+    }
+}
+
+fn run_counted(flags: &Flags, input: &[Sourced<String>], column: usize) -> io::Result<()> {
+    let records = read_counted(input, column)?;
+    let config = build_weighted_config(flags, &records)?;
+    let results = group_similar_weighted(&records, &config);
+
+    for (k, vs) in results.iter().filter(|(_, v)| flags.all || !v.is_empty()) {
+        let size = total_weight(std::iter::once(*k).chain(vs.iter().copied()));
+        if flags.json {
+            let members: Vec<serde_json::Value> = vs
+                .iter()
+                .map(|v| serde_json::json!({ "record": v.value, "count": v.count }))
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "representative": k.value,
+                    "count": k.count,
+                    "size": size,
+                    "members": members,
+                })
+            );
+        } else {
+            println!(
+                "{}  {}",
+                continued(&k.value, 0).green().bold(),
+                format!("×{}", size).green()
+            );
+            let members: Vec<String> = vs
+                .iter()
+                .map(|v| {
+                    format!(
+                        "{:>7}  {}",
+                        format!("×{}", v.count),
+                        v.value.dimmed().italic()
+                    )
+                })
+                .collect();
+            print_members(flags, &members);
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let flags = Flags::from_args();
+
+    if flags.command.is_some() && (flags.count_column.is_some() || flags.source) {
+        structopt::clap::Error::with_description(
+            "--count-column and --source cannot be used with a subcommand",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+
+    match &flags.command {
+        Some(Command::Query { corpus, k }) => return run_query(&flags, corpus, *k),
+        Some(Command::Link { left, right, k }) => return run_link(&flags, left, right, *k),
+        Some(Command::Diff {
+            before,
+            after,
+            change,
+        }) => return run_diff(&flags, before, after, *change),
+        None => {}
+    }
+
+    if flags.show_normalized {
+        return run_show_normalized(&flags);
+    }
+
+    if let Algorithm::Leader = flags.algorithm {
+        if !matches!(flags.metric(), Metric::Jaro) {
+            eprintln!("--algorithm leader requires --metric jaro");
+            std::process::exit(2);
+        }
+        return run_leader(&flags);
+    }
+
+    let records = input_records(&flags)?.collect::<io::Result<Vec<_>>>()?;
+    let input: Vec<&str> = records.iter().map(|r| r.value.as_str()).collect();
+
+    if let Some(column) = flags.count_column {
+        return run_counted(&flags, &records, column);
+    }
+
+    if flags.source {
+        return run_sourced(&flags, &records);
+    }
+
+    let config = build_config(&flags, &input)?;
+
+    if flags.check_blocking {
+        let report = blocking_report(&input, &config, flags.sample, flags.target_recall);
+        if flags.json {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "sampled": report.recall.sampled,
+                    "candidate_pairs": report.candidate_pairs,
+                    "total_pairs": report.total_pairs,
+                    "true_matches": report.recall.true_matches,
+                    "lost": report.recall.lost,
+                    "pair_recall": report.recall.recall(),
+                    "adjusted_rand_index": report.adjusted_rand_index,
+                    "suggested_tau": report.suggested_tau,
+                })
+            );
+        } else {
+            println!("sampled records:  {}", report.recall.sampled);
+            println!(
+                "candidate pairs:  {} of {} ({:.2}%)",
+                report.candidate_pairs,
+                report.total_pairs,
+                100.0 * report.candidate_pairs as f64 / report.total_pairs.max(1) as f64
+            );
+            println!(
+                "pair recall:      {:.4} ({} of {} true matches lost)",
+                report.recall.recall(),
+                report.recall.lost,
+                report.recall.true_matches
+            );
+            println!("adjusted Rand:    {:.4}", report.adjusted_rand_index);
+            match report.suggested_tau {
+                Some(tau) => println!(
+                    "suggested --tau:  {:.2} (pair recall >= {})",
+                    tau, flags.target_recall
+                ),
+                None if matches!(flags.blocking(), Some(BlockingKind::QGram))
+                    && !flags.no_blocked
+                    && !matches!(flags.metric(), Metric::Levenshtein) =>
+                {
+                    println!(
+                        "suggested --tau:  none reaches pair recall >= {}",
+                        flags.target_recall
+                    )
+                }
+                None => {}
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(k) = flags.neighbors {
+        let neighbors = similar_neighbors(&input, &config, k);
+        if flags.json {
+            let rendered: HashMap<&str, Vec<(&str, f32)>> = neighbors
+                .iter()
+                .map(|(r, ns)| (**r, ns.iter().map(|(n, d)| (**n, d.value())).collect()))
+                .collect();
+            println!("{}", serde_json::to_string(&rendered).unwrap());
+        } else {
+            for (r, ns) in &neighbors {
+                println!("{}", continued(r, 0).green().bold());
+                for (n, d) in ns {
+                    println!("   {:.3}  {}", d.value(), continued(n, 3).dimmed().italic());
+                }
+                println!();
+            }
+        }
+        return Ok(());
+    }
+
+    if flags.pairs {
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        if !flags.json {
+            writeln!(out, "i,j,distance")?;
+        }
+        for (i, j, d) in similar_pairs(&input, &config, flags.max_distance.as_ref()) {
+            if flags.json {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({ "i": i, "j": j, "distance": d.value() })
+                )?;
+            } else {
+                writeln!(out, "{},{},{}", i, j, d.value())?;
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(sample) = flags.recall {
+        let estimate = estimate_recall(&input, &config, sample);
+        eprintln!(
+            "blocking recall: {:.3} ({} of {} true matches lost across {} sampled records)",
+            estimate.recall(),
+            estimate.lost,
+            estimate.true_matches,
+            estimate.sampled
+        );
+    }
+
+    if flags.collapse {
+        print_collapsed(&flags, &group_similar_collapsed(&input, &config));
+        return Ok(());
+    }
+
+    let results: BTreeMap<&&str, Vec<&&str>> = group_similar(&input, &config);
+
+    if flags.templates {
+        let normalize = normalizer(&flags)?;
+        let mut templates: Vec<(usize, String)> = results
+            .iter()
+            .filter(|(_, v)| flags.all || !v.is_empty())
+            .map(|(k, vs)| {
+                let keys: Vec<String> =
+                    std::iter::once(k).chain(vs).map(|r| normalize(r)).collect();
+                (keys.len(), cluster_template(&keys))
+            })
+            .collect();
+        templates.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        for (size, template) in &templates {
+            if flags.json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "size": size, "template": template })
+                );
+            } else {
+                println!("{:>7}  {}", size.to_string().green().bold(), template);
+            }
+        }
+        return Ok(());
+    }
+
+    if flags.stats {
+        for (k, vs) in results.iter().filter(|(_, v)| flags.all || !v.is_empty()) {
+            let members: Vec<&str> = std::iter::once(**k).chain(vs.iter().map(|v| **v)).collect();
+            let values = normalize::extracted_values(&members);
+            if flags.json {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "representative": k,
+                        "size": members.len(),
+                        "values": values,
+                    })
+                );
+            } else {
+                println!("{}", continued(k, 0).green().bold());
+                println!("   {} records", members.len());
+                for (pattern, distinct) in &values {
+                    let shown: Vec<&str> = distinct
+                        .iter()
+                        .take(STATS_VALUES)
+                        .map(String::as_str)
+                        .collect();
+                    let more = if distinct.len() > STATS_VALUES {
+                        ", …"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "   {} ({} distinct): {}{}",
+                        pattern,
+                        distinct.len(),
+                        shown.join(", ").dimmed(),
+                        more
+                    );
+                }
+                println!();
+            }
+        }
+        return Ok(());
+    }
+
+    if flags.timeline {
+        print_timelines(&flags, &results);
+        return Ok(());
+    }
+
+    if let (Some(n), true) = (flags.top_values, flags.json) {
+        for (k, vs) in results.iter().filter(|(_, v)| flags.all || !v.is_empty()) {
+            let members: Vec<&str> = std::iter::once(**k).chain(vs.iter().map(|v| **v)).collect();
+            let fields: Vec<serde_json::Value> = normalize::value_histograms(&members)
+                .iter()
+                .map(|h| {
+                    serde_json::json!({
+                        "position": h.position,
+                        "pattern": h.pattern,
+                        "label": h.label,
+                        "distinct": h.counts.len(),
+                        "top": &h.counts[..h.counts.len().min(n)],
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "representative": k,
+                    "members": vs,
+                    "top_values": fields,
+                })
+            );
+        }
+    } else if flags.json {
         println!(
             "{}",
             serde_json::to_string(
@@ -118,12 +1490,17 @@ fn main() -> io::Result<()> {
         );
     } else {
         for (k, vs) in results.iter().filter(|(_, v)| flags.all || !v.is_empty()) {
-            println!("{}", k.green().bold());
+            println!("{}", continued(k, 0).green().bold());
 
-            for v in vs {
-                println!("   {}", v.dimmed().italic());
+            if let Some(n) = flags.top_values {
+                let members: Vec<&str> =
+                    std::iter::once(**k).chain(vs.iter().map(|v| **v)).collect();
+                print_top_values(&normalize::value_histograms(&members), n);
             }
 
+            let lines: Vec<ColoredString> = vs.iter().map(|v| v.dimmed().italic()).collect();
+            print_members(&flags, &lines);
+
             println!();
         }
     }
@@ -6,17 +6,39 @@
 //!
 //! # Recommended entry point
 //!
-//! [`default_normalizer`] fuses the four built-in patterns into a single-pass
-//! scanner: `0x`-prefixed hex addresses, bare hex tokens, quoted timestamps,
-//! and decimal integer sequences are each replaced with a fixed placeholder.
-//! Inputs with no digits are returned untouched.
+//! [`default_normalizer`] fuses the built-in patterns into a single-pass
+//! scanner: URLs, email addresses, filesystem paths, `0x`-prefixed hex
+//! addresses, UUIDs, bare hex tokens, quoted and ISO-8601 timestamps, IPv4
+//! and IPv6 addresses, durations, and decimal integer sequences are each
+//! replaced with a fixed placeholder. Inputs with none of digits, `/`, `\`
+//! or `@` are returned untouched.
+//!
+//! [`default_normalizer_with`] and the `_with` variants of the pattern
+//! functions take a [`Placeholders`] mode; `Typed` placeholders keep the
+//! value's shape (`<num:6>`, `<hex:16>`, `<ts>`), so ids of different lengths
+//! stay apart.
 //!
 //! # Custom pipelines
 //!
-//! For finer control, the individual pattern functions ([`hex_addresses`],
-//! [`numeric_ids`], [`timestamps`], [`hex_tokens`]) can be combined with
-//! [`compose`] or [`compose_all`]. Use [`identity`] when no normalization is
-//! desired.
+//! [`named`] builds a normalizer from a list of pattern groups, transforms
+//! and presets such as `logs` or `merchants`. For finer control, the
+//! individual pattern functions ([`hex_addresses`], [`numeric_ids`],
+//! [`timestamps`], [`hex_tokens`], [`uuids`], [`ipv4_addresses`],
+//! [`ipv6_addresses`], [`emails`], [`urls`], [`paths`], [`durations`],
+//! [`iso_timestamps`]) can be combined with [`compose`] or [`compose_all`].
+//! Use [`identity`] when no normalization is desired.
+//!
+//! # Entity names
+//!
+//! [`entity_normalizer`] canonicalizes names rather than machine output:
+//! [`nfkc`] (or the narrower [`fullwidth_to_ascii`]), [`remove_diacritics`],
+//! [`lowercase`], [`strip_punctuation`] and [`collapse_whitespace`].
+//!
+//! # User-defined rules
+//!
+//! [`Rules`] compiles regex rules — typically loaded from a TOML file with
+//! [`Rules::from_toml`] — into the same scan, ahead of (or instead of) the
+//! built-in patterns; [`rules_normalizer`] turns them into a normalizer.
 //!
 //! # Internal layout
 //!
@@ -25,7 +47,16 @@
 //! [`patterns`] holds one submodule per pattern, each exposing an
 //! `is_start` / `consume` pair that the engine dispatches through.
 
-use engine::{replace_pattern, replace_patterns, Matcher};
+use engine::{replace_pattern, replace_patterns, scan, Matcher};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// A boxed normalizer, as returned by the constructors in this module and
+/// accepted by [`crate::Config::with_normalizer`].
+pub type Normalizer = Box<dyn Fn(&str) -> String + Send + Sync>;
 
 /// Identity normalizer — returns the input unchanged.
 pub fn identity(input: &str) -> String {
@@ -37,41 +68,722 @@ pub fn identity(input: &str) -> String {
 ///
 /// Uses a fused single-pass scanner (one allocation, one scan) rather than
 /// chaining individual normalizers. Pattern priority order:
-/// 1. `hex_addresses` — match `0x`-prefixed hex first so later patterns don't split them
-/// 2. `hex_tokens` — match bare hex tokens (8+ hex chars) before `numeric_ids` would consume their digit runs
-/// 3. `timestamps` — match quoted `"YYYY-MM-DD ..."` timestamps before `numeric_ids` would replace the digits inside
-/// 4. `numeric_ids` — most permissive, checked last to catch remaining standalone digit sequences
-pub fn default_normalizer() -> Box<dyn Fn(&str) -> String + Send + Sync> {
-    const MATCHERS: &[Matcher] = &[
-        (
-            patterns::hex_addresses::is_start,
-            patterns::hex_addresses::consume,
-        ),
-        (
-            patterns::hex_tokens::is_start,
-            patterns::hex_tokens::consume,
-        ),
-        (
-            patterns::timestamps::is_start,
-            patterns::timestamps::consume,
-        ),
-        (
-            patterns::numeric_ids::is_start,
-            patterns::numeric_ids::consume,
-        ),
-    ];
-
-    Box::new(|input| {
-        // All patterns require ASCII digits; strings without digits skip scanning.
-        if !input.bytes().any(|b| b.is_ascii_digit()) {
+/// 1. `urls` — whole URLs first, so their hosts, paths and query strings
+///    aren't matched piecemeal
+/// 2. `emails` — before `paths` and the numeric patterns split the domain
+/// 3. `paths` — absolute, `~/` and drive-letter paths of two or more segments
+/// 4. `hex_addresses` — match `0x`-prefixed hex first so later patterns don't
+///    split them
+/// 5. `uuids` — before `hex_tokens` would take the first 8-char group alone
+/// 6. `hex_tokens` — match bare hex tokens (8+ hex chars) before
+///    `numeric_ids` would consume their digit runs
+/// 7. `timestamps` — match quoted `"YYYY-MM-DD ..."` timestamps before
+///    `numeric_ids` would replace the digits inside
+/// 8. `iso_timestamps` — unquoted ISO-8601 dates and times, before
+///    `ipv6_addresses` sees `HH:MM`
+/// 9. `ipv4_addresses` and `ipv6_addresses`
+/// 10. `durations` — numbers with a time unit suffix, which `numeric_ids`
+///     leaves alone
+/// 11. `numeric_ids` — most permissive, checked last to catch remaining
+///     standalone digit sequences
+pub fn default_normalizer() -> Normalizer {
+    default_normalizer_with(Placeholders::Fixed)
+}
+
+/// [`default_normalizer`] writing matches as `placeholders`.
+///
+/// ```
+/// use group_similar::normalize::{self, Placeholders};
+///
+/// let typed = normalize::default_normalizer_with(Placeholders::Typed { keep_prefix: 0 });
+/// assert_eq!(typed("store 042 txn 918273645012"), "store <num:3> txn <num:12>");
+///
+/// let prefixed = normalize::default_normalizer_with(Placeholders::Typed { keep_prefix: 2 });
+/// assert_eq!(prefixed("store 042"), "store <num:3:04>");
+/// ```
+pub fn default_normalizer_with(placeholders: Placeholders) -> Normalizer {
+    pattern_normalizer(DEFAULT_MATCHERS.to_vec(), placeholders)
+}
+
+/// How the built-in patterns write what they replace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Placeholders {
+    /// One fixed placeholder per pattern (`0`, `_hex_`, `_timestamp_`, ...),
+    /// so values of any length share a key.
+    #[default]
+    Fixed,
+    /// Placeholders naming the kind of value, with the length for numbers
+    /// and hex (`<num:6>`, `0x<hex:12>`, `<hex:16>`, `<uuid>`, `<ts>`), so a
+    /// 3-digit store number and a 12-digit transaction id no longer share a
+    /// key. When `keep_prefix` is non-zero, up to that many leading
+    /// characters of the value are kept after the shape (`<num:6:10>`), cut
+    /// short at whitespace. Placeholders already in the input are left as
+    /// they are.
+    Typed {
+        /// Characters of the original value to keep.
+        keep_prefix: usize,
+    },
+}
+
+impl Placeholders {
+    /// Append the placeholder for `value`, matched by `matcher`.
+    fn write(self, out: &mut String, matcher: &Matcher, value: &str, fixed: &'static str) {
+        let keep_prefix = match self {
+            Placeholders::Fixed => return out.push_str(fixed),
+            Placeholders::Typed { keep_prefix } => keep_prefix,
+        };
+        if matcher.tag.is_empty() {
+            return out.push_str(value);
+        }
+
+        // Quoted timestamps keep their quotes, as with fixed placeholders.
+        let (quote, mut value) = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(inner) => ("\"", inner),
+            None => ("", value),
+        };
+        out.push_str(quote);
+        if let Some(lead) = matcher.sized {
+            out.push_str(&value[..lead]);
+            value = &value[lead..];
+        }
+        out.push('<');
+        out.push_str(matcher.tag);
+        if matcher.sized.is_some() {
+            out.push(':');
+            out.push_str(&value.chars().count().to_string());
+        }
+        let prefix: String = value
+            .chars()
+            .take(keep_prefix)
+            .take_while(|c| !c.is_whitespace() && !matches!(c, '<' | '>'))
+            .collect();
+        if !prefix.is_empty() {
+            out.push(':');
+            out.push_str(&prefix);
+        }
+        out.push('>');
+        out.push_str(quote);
+    }
+}
+
+/// Fused scanner over `matchers`, skipping strings no built-in pattern can
+/// match.
+fn pattern_normalizer(mut matchers: Vec<Matcher>, placeholders: Placeholders) -> Normalizer {
+    if placeholders != Placeholders::Fixed {
+        // Typed placeholders contain digits; pass existing ones through so
+        // normalizing twice changes nothing.
+        matchers.insert(0, TYPED_PLACEHOLDERS);
+    }
+    Box::new(move |input| {
+        // Every pattern needs a digit except URLs, paths and emails, which
+        // need a `/`, `\` or `@`; other strings skip scanning.
+        if !input
+            .bytes()
+            .any(|b| b.is_ascii_digit() || matches!(b, b'/' | b'\\' | b'@'))
+        {
             return input.to_string();
         }
-        replace_patterns(input, MATCHERS)
+        match placeholders {
+            Placeholders::Fixed => replace_patterns(input, &matchers),
+            _ => scan(input, true, &matchers, None, |out, matcher, span, fixed| {
+                placeholders.write(out, matcher, &input[span], fixed)
+            }),
+        }
+    })
+}
+
+/// One value replaced by a built-in pattern, as reported by
+/// [`capture_patterns`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// Name of the pattern's normalizer function, e.g. `numeric_ids`.
+    pub pattern: &'static str,
+    /// Byte range of the replaced value in the input.
+    pub span: Range<usize>,
+    /// What the value was replaced with.
+    pub replacement: String,
+}
+
+/// [`default_normalizer_with`]'s output for `input`, along with every value
+/// it replaced, in input order.
+///
+/// ```
+/// use group_similar::normalize::{self, Placeholders};
+///
+/// let input = "order 1234 failed after 30ms";
+/// let (key, captures) = normalize::capture_patterns(input, Placeholders::Fixed);
+/// assert_eq!(key, "order 0 failed after _duration_");
+/// assert_eq!(captures[0].pattern, "numeric_ids");
+/// assert_eq!(&input[captures[0].span.clone()], "1234");
+/// assert_eq!(captures[1].replacement, "_duration_");
+/// ```
+pub fn capture_patterns(input: &str, placeholders: Placeholders) -> (String, Vec<Capture>) {
+    let mut matchers = DEFAULT_MATCHERS.to_vec();
+    if placeholders != Placeholders::Fixed {
+        matchers.insert(0, TYPED_PLACEHOLDERS);
+    }
+    let mut captures = Vec::new();
+    let typed = placeholders != Placeholders::Fixed;
+    let key = scan(
+        input,
+        typed,
+        &matchers,
+        None,
+        |out, matcher, span, fixed| {
+            let before = out.len();
+            placeholders.write(out, matcher, &input[span.clone()], fixed);
+            if !matcher.tag.is_empty() {
+                captures.push(Capture {
+                    pattern: matcher.name,
+                    replacement: out[before..].to_string(),
+                    span,
+                });
+            }
+        },
+    );
+    (key, captures)
+}
+
+/// The distinct values the built-in patterns replace across `records`, by
+/// pattern name, each sorted.
+pub fn extracted_values<S: AsRef<str>>(records: &[S]) -> BTreeMap<&'static str, Vec<String>> {
+    let mut values: BTreeMap<&'static str, BTreeSet<&str>> = BTreeMap::new();
+    for record in records {
+        let record = record.as_ref();
+        for capture in capture_patterns(record, Placeholders::Fixed).1 {
+            values
+                .entry(capture.pattern)
+                .or_default()
+                .insert(&record[capture.span]);
+        }
+    }
+    values
+        .into_iter()
+        .map(|(pattern, set)| (pattern, set.into_iter().map(String::from).collect()))
+        .collect()
+}
+
+/// The values replaced at one placeholder position across a set of records,
+/// as returned by [`value_histograms`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueHistogram {
+    /// Index of the value among those replaced in each record.
+    pub position: usize,
+    /// Name of the pattern that replaced it, e.g. `numeric_ids`.
+    pub pattern: &'static str,
+    /// The last word before the value in the first record with one at this
+    /// position (`parent_id` for `@parent_id=1234`); empty at the start.
+    pub label: String,
+    /// Distinct values with their counts, most frequent first and ties in
+    /// value order.
+    pub counts: Vec<(String, usize)>,
+}
+
+impl ValueHistogram {
+    /// Number of values counted.
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|(_, n)| n).sum()
+    }
+
+    /// Smallest and largest value in string order — for timestamps, the
+    /// time range.
+    pub fn range(&self) -> Option<(&str, &str)> {
+        let min = self.counts.iter().map(|(v, _)| v.as_str()).min()?;
+        let max = self.counts.iter().map(|(v, _)| v.as_str()).max()?;
+        Some((min, max))
+    }
+}
+
+/// Histograms of the values the built-in patterns replace across `records`,
+/// one per placeholder position and pattern, in position order.
+///
+/// Records in one cluster usually share their placeholders, so position `k`
+/// tracks the same field in each: the third value of every
+/// `#<Widget:0x_ @parent_id=0 ...>` line is its parent id.
+///
+/// ```
+/// use group_similar::normalize;
+///
+/// let histograms = normalize::value_histograms(&[
+///     "job 17 failed on host 3",
+///     "job 18 failed on host 3",
+///     "job 19 failed on host 4",
+/// ]);
+/// assert_eq!(histograms[1].label, "host");
+/// assert_eq!(histograms[1].counts, vec![("3".to_string(), 2), ("4".to_string(), 1)]);
+/// ```
+pub fn value_histograms<S: AsRef<str>>(records: &[S]) -> Vec<ValueHistogram> {
+    let mut fields: BTreeMap<(usize, &'static str), ValueHistogram> = BTreeMap::new();
+    let mut counts: HashMap<(usize, &'static str, &str), usize> = HashMap::new();
+    for record in records {
+        let record = record.as_ref();
+        for (position, capture) in capture_patterns(record, Placeholders::Fixed)
+            .1
+            .into_iter()
+            .enumerate()
+        {
+            fields
+                .entry((position, capture.pattern))
+                .or_insert_with(|| ValueHistogram {
+                    position,
+                    pattern: capture.pattern,
+                    label: label(&record[..capture.span.start]),
+                    counts: Vec::new(),
+                });
+            *counts
+                .entry((position, capture.pattern, &record[capture.span]))
+                .or_insert(0) += 1;
+        }
+    }
+
+    for ((position, pattern, value), n) in counts {
+        let field = fields.get_mut(&(position, pattern)).expect("counted field");
+        field.counts.push((value.to_string(), n));
+    }
+    fields
+        .into_values()
+        .map(|mut field| {
+            field
+                .counts
+                .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            field
+        })
+        .collect()
+}
+
+/// The last run of word characters in `prefix`.
+fn label(prefix: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let trimmed = prefix.trim_end_matches(|c: char| !is_word(c));
+    let start = trimmed
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_word(c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    trimmed[start..].to_string()
+}
+
+/// Fused scanner for the single built-in pattern `name`.
+fn single_pattern(name: &str, placeholders: Placeholders) -> Normalizer {
+    pattern_normalizer(
+        DEFAULT_MATCHERS
+            .iter()
+            .filter(|m| m.name == name)
+            .copied()
+            .collect(),
+        placeholders,
+    )
+}
+
+/// Matches typed placeholders written by an earlier pass and copies them.
+const TYPED_PLACEHOLDERS: Matcher = Matcher::new(
+    "typed_placeholders",
+    "",
+    patterns::typed_placeholders::is_start,
+    patterns::typed_placeholders::consume,
+);
+
+/// The built-in patterns in [`default_normalizer`]'s priority order.
+const DEFAULT_MATCHERS: &[Matcher] = &[
+    Matcher::new(
+        "urls",
+        "url",
+        patterns::urls::is_start,
+        patterns::urls::consume,
+    ),
+    Matcher::new(
+        "emails",
+        "email",
+        patterns::emails::is_start,
+        patterns::emails::consume,
+    ),
+    Matcher::new(
+        "paths",
+        "path",
+        patterns::paths::is_start,
+        patterns::paths::consume,
+    ),
+    Matcher::new(
+        "hex_addresses",
+        "hex",
+        patterns::hex_addresses::is_start,
+        patterns::hex_addresses::consume,
+    )
+    .sized(2),
+    Matcher::new(
+        "uuids",
+        "uuid",
+        patterns::uuids::is_start,
+        patterns::uuids::consume,
+    ),
+    Matcher::new(
+        "hex_tokens",
+        "hex",
+        patterns::hex_tokens::is_start,
+        patterns::hex_tokens::consume,
+    )
+    .sized(0),
+    Matcher::new(
+        "timestamps",
+        "ts",
+        patterns::timestamps::is_start,
+        patterns::timestamps::consume,
+    ),
+    Matcher::new(
+        "iso_timestamps",
+        "ts",
+        patterns::iso_timestamps::is_start,
+        patterns::iso_timestamps::consume,
+    ),
+    Matcher::new(
+        "ipv4_addresses",
+        "ipv4",
+        patterns::ipv4_addresses::is_start,
+        patterns::ipv4_addresses::consume,
+    ),
+    Matcher::new(
+        "ipv6_addresses",
+        "ipv6",
+        patterns::ipv6_addresses::is_start,
+        patterns::ipv6_addresses::consume,
+    ),
+    Matcher::new(
+        "durations",
+        "duration",
+        patterns::durations::is_start,
+        patterns::durations::consume,
+    ),
+    Matcher::new(
+        "numeric_ids",
+        "num",
+        patterns::numeric_ids::is_start,
+        patterns::numeric_ids::consume,
+    )
+    .sized(0),
+];
+
+/// One user-defined normalization rule: every match of `pattern` is replaced
+/// with the literal `replacement`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Identifies the rule in error messages.
+    pub name: String,
+    /// Regular expression, in [`regex`] syntax.
+    pub pattern: String,
+    /// Placeholder substituted for each match.
+    pub replacement: String,
+    /// Rules with higher priority win when several match at the same
+    /// position; ties go to the rule listed first.
+    #[serde(default)]
+    pub priority: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default = "default_true")]
+    defaults: bool,
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A compiled set of [`Rule`]s.
+///
+/// All rules are fused into one leftmost-first regex, so the input is still
+/// scanned once: at each position the highest-priority rule matching there
+/// wins, then (when enabled) the built-in patterns of
+/// [`default_normalizer`], and otherwise the character is kept.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    regex: Regex,
+    /// Capture-group index wrapping each rule, in priority order.
+    groups: Vec<usize>,
+    replacements: Vec<String>,
+    defaults: bool,
+}
+
+impl Rules {
+    /// Compile `rules`, followed by the built-in patterns when `defaults`.
+    pub fn new(mut rules: Vec<Rule>, defaults: bool) -> Result<Self, String> {
+        for rule in &rules {
+            Regex::new(&rule.pattern).map_err(|e| format!("rule '{}': {}", rule.name, e))?;
+        }
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+
+        let alternation: Vec<String> = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| format!("(?P<__rule{}>{})", i, rule.pattern))
+            .collect();
+        // An empty alternation would match everywhere; `[^\s\S]` never does.
+        let combined = if alternation.is_empty() {
+            "[^\\s\\S]".to_string()
+        } else {
+            alternation.join("|")
+        };
+        let regex = Regex::new(&combined).map_err(|e| e.to_string())?;
+        let groups = (0..rules.len())
+            .map(|i| {
+                regex
+                    .capture_names()
+                    .position(|name| name == Some(&format!("__rule{}", i)))
+                    .expect("every rule has a named group")
+            })
+            .collect();
+
+        Ok(Self {
+            regex,
+            groups,
+            replacements: rules.into_iter().map(|rule| rule.replacement).collect(),
+            defaults,
+        })
+    }
+
+    /// Parse and compile a rule file:
+    ///
+    /// ```toml
+    /// # Also apply the built-in patterns after the rules (default: true).
+    /// defaults = true
+    ///
+    /// [[rule]]
+    /// name = "tenant"
+    /// pattern = "tenant-[a-z0-9]+"
+    /// replacement = "<tenant>"
+    /// priority = 10
+    /// ```
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let file: RuleFile = toml::from_str(source).map_err(|e| e.to_string())?;
+        Self::new(file.rules, file.defaults)
+    }
+
+    /// The first non-empty rule match starting at or after `start`, as
+    /// `(start, end, replacement)`.
+    fn find_at(&self, input: &str, start: usize) -> Option<(usize, usize, &str)> {
+        let captures = self.regex.captures_at(input, start)?;
+        self.groups
+            .iter()
+            .zip(&self.replacements)
+            .find_map(|(&group, replacement)| {
+                captures
+                    .get(group)
+                    .map(|m| (m.start(), m.end(), replacement.as_str()))
+            })
+    }
+}
+
+/// Normalizer applying user-defined [`Rules`] in a single scan.
+pub fn rules_normalizer(rules: Rules) -> Normalizer {
+    Box::new(move |input| {
+        let matchers: &[Matcher] = if rules.defaults {
+            DEFAULT_MATCHERS
+        } else {
+            &[]
+        };
+        scan(
+            input,
+            false,
+            matchers,
+            Some(&rules),
+            |result, _, _, replacement| result.push_str(replacement),
+        )
     })
 }
 
+/// Pattern groups accepted by [`named`], each selecting the built-in patterns
+/// (by function name) it fuses into the scan.
+const PATTERN_GROUPS: &[(&str, &[&str])] = &[
+    ("url", &["urls"]),
+    ("email", &["emails"]),
+    ("path", &["paths"]),
+    ("hex", &["hex_addresses", "hex_tokens"]),
+    ("uuid", &["uuids"]),
+    ("timestamp", &["timestamps", "iso_timestamps"]),
+    ("ip", &["ipv4_addresses", "ipv6_addresses"]),
+    ("duration", &["durations"]),
+    ("numeric", &["numeric_ids"]),
+];
+
+type Transform = fn(&str) -> String;
+
+/// Whole-string transforms accepted by [`named`], applied after the patterns.
+const TRANSFORMS: &[(&str, Transform)] = &[
+    ("lowercase", lowercase),
+    ("whitespace", collapse_whitespace),
+    ("punctuation", strip_punctuation),
+    ("diacritics", remove_diacritics),
+    ("nfkc", nfkc),
+    ("fullwidth", fullwidth_to_ascii),
+];
+
+/// Presets accepted by [`named`], as specs of their own.
+const PRESETS: &[(&str, &str)] = &[
+    (
+        "default",
+        "url,email,path,hex,uuid,timestamp,ip,duration,numeric",
+    ),
+    ("logs", "default,whitespace"),
+    ("entity", "nfkc,diacritics,lowercase,punctuation,whitespace"),
+    ("merchants", "numeric,entity"),
+    ("stacktraces", "hex,uuid,timestamp,numeric,whitespace"),
+    ("none", ""),
+];
+
+/// Build a normalizer from a comma-separated list of names, as accepted by
+/// the CLI's `--normalize`.
+///
+/// - Pattern groups — `url`, `email`, `path`, `hex`, `uuid`, `timestamp`,
+///   `ip`, `duration`, `numeric` — are fused into one scan in
+///   [`default_normalizer`]'s priority order, whatever order they're listed
+///   in.
+/// - Transforms — `lowercase`, `whitespace`, `punctuation`, `diacritics`,
+///   `nfkc`, `fullwidth` — then run in the order listed, chained with
+///   [`compose_all`].
+/// - Presets expand to a list of the above: `default` (every pattern, the
+///   same as [`default_normalizer`]), `logs` (`default,whitespace`),
+///   `entity` (the same as [`entity_normalizer`]), `merchants`
+///   (`numeric,entity`), `stacktraces`
+///   (`hex,uuid,timestamp,numeric,whitespace` — paths and method names are
+///   kept, line numbers are not) and `none`.
+///
+/// ```
+/// use group_similar::normalize;
+///
+/// let norm = normalize::named("numeric,lowercase").unwrap();
+/// assert_eq!(norm("McDonalds 105109"), "mcdonalds 0");
+/// assert!(normalize::named("numerics").is_err());
+/// ```
+pub fn named(spec: &str) -> Result<Normalizer, String> {
+    named_with(spec, Placeholders::Fixed)
+}
+
+/// [`named`] with the pattern groups writing matches as `placeholders`.
+pub fn named_with(spec: &str, placeholders: Placeholders) -> Result<Normalizer, String> {
+    let mut patterns: Vec<&str> = Vec::new();
+    let mut transforms: Vec<Transform> = Vec::new();
+    expand_spec(spec, &mut patterns, &mut transforms)?;
+
+    let mut normalizers: Vec<Normalizer> = Vec::new();
+    if !patterns.is_empty() {
+        normalizers.push(pattern_normalizer(
+            DEFAULT_MATCHERS
+                .iter()
+                .filter(|m| patterns.contains(&m.name))
+                .copied()
+                .collect(),
+            placeholders,
+        ));
+    }
+    normalizers.extend(transforms.into_iter().map(|f| Box::new(f) as Normalizer));
+    Ok(compose_all(normalizers))
+}
+
+fn expand_spec(
+    spec: &str,
+    patterns: &mut Vec<&'static str>,
+    transforms: &mut Vec<Transform>,
+) -> Result<(), String> {
+    for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if let Some((_, expansion)) = PRESETS.iter().find(|(n, _)| *n == name) {
+            expand_spec(expansion, patterns, transforms)?;
+        } else if let Some((_, names)) = PATTERN_GROUPS.iter().find(|(n, _)| *n == name) {
+            patterns.extend_from_slice(names);
+        } else if let Some((_, f)) = TRANSFORMS.iter().find(|(n, _)| *n == name) {
+            transforms.push(*f);
+        } else {
+            let known: Vec<&str> = PATTERN_GROUPS
+                .iter()
+                .map(|(n, _)| *n)
+                .chain(TRANSFORMS.iter().map(|(n, _)| *n))
+                .chain(PRESETS.iter().map(|(n, _)| *n))
+                .collect();
+            return Err(format!(
+                "unknown normalizer '{}'; expected one of: {}",
+                name,
+                known.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Lowercase the input (Unicode-aware).
+pub fn lowercase(input: &str) -> String {
+    input.to_lowercase()
+}
+
+/// Trim the input and collapse every run of whitespace to a single space.
+pub fn collapse_whitespace(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drop apostrophes (`McDonald's` → `McDonalds`) and replace every other
+/// character that is neither alphanumeric nor whitespace with a space
+/// (`Target.com` → `Target com`).
+pub fn strip_punctuation(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '\u{2019}'))
+        .map(|c| {
+            if c.is_alphanumeric() || c.is_whitespace() {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect()
+}
+
+/// Remove diacritics by decomposing each character and dropping combining
+/// marks (`José` → `Jose`, `Ångström` → `Angstrom`).
+pub fn remove_diacritics(input: &str) -> String {
+    input
+        .nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .nfc()
+        .collect()
+}
+
+/// Unicode NFKC compatibility folding: ligatures, full-width and other
+/// presentation forms become their plain equivalents (`ﬁ` → `fi`, `①` → `1`).
+pub fn nfkc(input: &str) -> String {
+    input.nfkc().collect()
+}
+
+/// Map full-width ASCII variants (`ＴＡＲＧＥＴ　１２`) and the ideographic
+/// space to ASCII, leaving everything else alone. A narrower alternative to
+/// [`nfkc`].
+pub fn fullwidth_to_ascii(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| match c {
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Canonicalization for entity names such as merchants or people: NFKC
+/// folding, diacritic removal, lowercasing, punctuation stripping and
+/// whitespace collapsing, in that order. `TARGET `, `Target` and `Ｔａｒｇｅｔ`
+/// share a key, as do `José's Café` and `Jose's cafe`.
+pub fn entity_normalizer() -> Normalizer {
+    compose_all(vec![
+        Box::new(nfkc),
+        Box::new(remove_diacritics),
+        Box::new(lowercase),
+        Box::new(strip_punctuation),
+        Box::new(collapse_whitespace),
+    ])
+}
+
 /// Compose two normalizers into one that applies `first`, then `second`.
-pub fn compose<F, G>(first: F, second: G) -> Box<dyn Fn(&str) -> String + Send + Sync>
+pub fn compose<F, G>(first: F, second: G) -> Normalizer
 where
     F: Fn(&str) -> String + Send + Sync + 'static,
     G: Fn(&str) -> String + Send + Sync + 'static,
@@ -80,9 +792,7 @@ where
 }
 
 /// Compose a list of normalizers left-to-right.
-pub fn compose_all(
-    normalizers: Vec<Box<dyn Fn(&str) -> String + Send + Sync>>,
-) -> Box<dyn Fn(&str) -> String + Send + Sync> {
+pub fn compose_all(normalizers: Vec<Normalizer>) -> Normalizer {
     Box::new(move |input| {
         let mut result = input.to_string();
         for f in &normalizers {
@@ -101,6 +811,11 @@ pub fn hex_addresses(input: &str) -> String {
     )
 }
 
+/// [`hex_addresses`] writing matches as `placeholders`.
+pub fn hex_addresses_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("hex_addresses", placeholders)
+}
+
 /// Replace decimal integer sequences (e.g. `12345`) with `0`.
 pub fn numeric_ids(input: &str) -> String {
     replace_pattern(
@@ -110,6 +825,11 @@ pub fn numeric_ids(input: &str) -> String {
     )
 }
 
+/// [`numeric_ids`] writing matches as `placeholders`.
+pub fn numeric_ids_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("numeric_ids", placeholders)
+}
+
 /// Replace quoted timestamps matching `"YYYY-MM-DD HH:MM:SS..."` with a placeholder.
 pub fn timestamps(input: &str) -> String {
     replace_pattern(
@@ -119,6 +839,11 @@ pub fn timestamps(input: &str) -> String {
     )
 }
 
+/// [`timestamps`] writing matches as `placeholders`.
+pub fn timestamps_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("timestamps", placeholders)
+}
+
 /// Replace hex sequences of 8+ hex chars not preceded by `0x` (e.g. object tokens, hashes).
 pub fn hex_tokens(input: &str) -> String {
     replace_pattern(
@@ -128,107 +853,779 @@ pub fn hex_tokens(input: &str) -> String {
     )
 }
 
+/// [`hex_tokens`] writing matches as `placeholders`.
+pub fn hex_tokens_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("hex_tokens", placeholders)
+}
+
+/// Replace UUIDs (e.g. `123e4567-e89b-12d3-a456-426614174000`) with `_uuid_`.
+pub fn uuids(input: &str) -> String {
+    replace_pattern(input, patterns::uuids::is_start, patterns::uuids::consume)
+}
+
+/// [`uuids`] writing matches as `placeholders`.
+pub fn uuids_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("uuids", placeholders)
+}
+
+/// Replace dotted-quad IPv4 addresses (e.g. `10.0.12.7`) with `_ip_`. Any
+/// four dot-separated numbers match, so four-part version strings do too.
+pub fn ipv4_addresses(input: &str) -> String {
+    replace_pattern(
+        input,
+        patterns::ipv4_addresses::is_start,
+        patterns::ipv4_addresses::consume,
+    )
+}
+
+/// [`ipv4_addresses`] writing matches as `placeholders`.
+pub fn ipv4_addresses_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("ipv4_addresses", placeholders)
+}
+
+/// Replace IPv6 addresses (e.g. `fe80::1ff:fe23:4567:890a`) with `_ip_`.
+/// Addresses must contain a digit and either `::` or all eight groups, so
+/// `HH:MM:SS` times and `Module::Name` paths are left alone.
+pub fn ipv6_addresses(input: &str) -> String {
+    replace_pattern(
+        input,
+        patterns::ipv6_addresses::is_start,
+        patterns::ipv6_addresses::consume,
+    )
+}
+
+/// [`ipv6_addresses`] writing matches as `placeholders`.
+pub fn ipv6_addresses_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("ipv6_addresses", placeholders)
+}
+
+/// Replace email addresses (e.g. `jane.doe+tag@example.com`) with `_email_`.
+pub fn emails(input: &str) -> String {
+    replace_pattern(input, patterns::emails::is_start, patterns::emails::consume)
+}
+
+/// [`emails`] writing matches as `placeholders`.
+pub fn emails_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("emails", placeholders)
+}
+
+/// Replace URLs with a `scheme://` prefix, including any path and query
+/// string, with `_url_`.
+pub fn urls(input: &str) -> String {
+    replace_pattern(input, patterns::urls::is_start, patterns::urls::consume)
+}
+
+/// [`urls`] writing matches as `placeholders`.
+pub fn urls_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("urls", placeholders)
+}
+
+/// Replace filesystem paths of two or more segments (e.g.
+/// `/var/log/app/42/out.log`, `~/src/app`, `C:\Users\jane`) with `_path_`.
+/// A `:line` suffix is left in place.
+pub fn paths(input: &str) -> String {
+    replace_pattern(input, patterns::paths::is_start, patterns::paths::consume)
+}
+
+/// [`paths`] writing matches as `placeholders`.
+pub fn paths_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("paths", placeholders)
+}
+
+/// Replace durations such as `123ms`, `1.5s` or `1h30m` with `_duration_`.
+pub fn durations(input: &str) -> String {
+    replace_pattern(
+        input,
+        patterns::durations::is_start,
+        patterns::durations::consume,
+    )
+}
+
+/// [`durations`] writing matches as `placeholders`.
+pub fn durations_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("durations", placeholders)
+}
+
+/// Replace unquoted ISO-8601 dates and timestamps (e.g.
+/// `2026-03-25T03:56:10.370Z`, `2026-03-25 03:56:10 +0000`) with
+/// `_timestamp_`.
+pub fn iso_timestamps(input: &str) -> String {
+    replace_pattern(
+        input,
+        patterns::iso_timestamps::is_start,
+        patterns::iso_timestamps::consume,
+    )
+}
+
+/// [`iso_timestamps`] writing matches as `placeholders`.
+pub fn iso_timestamps_with(placeholders: Placeholders) -> Normalizer {
+    single_pattern("iso_timestamps", placeholders)
+}
+
 /// Byte-level scanner that dispatches through a list of `(is_start, consume)`
 /// matchers. All patterns share this engine so they can be fused into a
 /// single-pass scan.
 mod engine {
-    pub(super) type Matcher = (
-        fn(&[u8], usize) -> bool,
-        fn(&[u8], usize) -> Option<(usize, &'static str)>,
-    );
+    use std::ops::Range;
+
+    /// One pattern: `is_start` is a cheap check that a match may begin at a
+    /// byte offset, `consume` returns the match's end and placeholder.
+    #[derive(Clone, Copy)]
+    pub(super) struct Matcher {
+        /// Name of the pattern's public normalizer function.
+        pub(super) name: &'static str,
+        /// Kind in typed placeholders, e.g. `num` in `<num:6>`. Empty for
+        /// matches that are copied through unchanged.
+        pub(super) tag: &'static str,
+        /// For patterns whose length is part of their shape, the number of
+        /// leading bytes kept verbatim and not counted (`2` for `0x`).
+        pub(super) sized: Option<usize>,
+        pub(super) is_start: IsStart,
+        pub(super) consume: Consume,
+    }
+
+    pub(super) type IsStart = fn(&Scan, usize) -> bool;
+    pub(super) type Consume = fn(&Scan, usize) -> Option<(usize, &'static str)>;
+
+    /// The bytes a matcher runs over, and whether typed placeholders in them
+    /// stand for a word. Only scans passing typed placeholders through treat
+    /// them so: to a fixed-placeholder scan, `<num:3>` is text like any
+    /// other. Derefs to the bytes, so patterns index it like a slice.
+    #[derive(Clone, Copy)]
+    pub(super) struct Scan<'a> {
+        bytes: &'a [u8],
+        pub(super) typed: bool,
+    }
+
+    impl<'a> Scan<'a> {
+        pub(super) fn new(bytes: &'a [u8], typed: bool) -> Self {
+            Self { bytes, typed }
+        }
+    }
+
+    impl std::ops::Deref for Scan<'_> {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            self.bytes
+        }
+    }
+
+    impl Matcher {
+        pub(super) const fn new(
+            name: &'static str,
+            tag: &'static str,
+            is_start: IsStart,
+            consume: Consume,
+        ) -> Self {
+            Self {
+                name,
+                tag,
+                sized: None,
+                is_start,
+                consume,
+            }
+        }
+
+        /// Record the match length in typed placeholders, after skipping
+        /// `lead` bytes that are kept as they are.
+        pub(super) const fn sized(mut self, lead: usize) -> Self {
+            self.sized = Some(lead);
+            self
+        }
+    }
+
+    /// Single-pass scanner that tries multiple pattern matchers at each byte
+    /// position. First matching pattern wins. One allocation, one scan.
+    pub(super) fn replace_patterns(input: &str, matchers: &[Matcher]) -> String {
+        scan(input, false, matchers, None, |result, _, _, replacement| {
+            result.push_str(replacement)
+        })
+    }
+
+    /// [`replace_patterns`] with each match written by `emit`, which receives
+    /// the matcher, the match's byte range and the matcher's placeholder.
+    /// `typed` says whether typed placeholders in `input` stand for a word;
+    /// see [`Scan`].
+    ///
+    /// User-defined `rules` are tried before the matchers at each position,
+    /// each match written as its rule's replacement. The next rule match is
+    /// cached and only searched for again once the scan has moved past its
+    /// start.
+    pub(super) fn scan<E>(
+        input: &str,
+        typed: bool,
+        matchers: &[Matcher],
+        rules: Option<&super::Rules>,
+        mut emit: E,
+    ) -> String
+    where
+        E: FnMut(&mut String, &Matcher, Range<usize>, &'static str),
+    {
+        let bytes = &Scan::new(input.as_bytes(), typed);
+        let len = bytes.len();
+        let mut result = String::with_capacity(len);
+        let mut next = rules.and_then(|rules| rules.find_at(input, 0));
+        let mut i = 0;
+
+        while i < len {
+            if let Some(rules) = rules {
+                if matches!(next, Some((start, _, _)) if start < i) {
+                    next = rules.find_at(input, i);
+                }
+                if let Some((start, end, replacement)) = next {
+                    // Empty matches are skipped; the search resumes past them.
+                    if start == i && end > i {
+                        result.push_str(replacement);
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+
+            if bytes[i].is_ascii() {
+                let mut matched = false;
+                for matcher in matchers {
+                    if (matcher.is_start)(bytes, i) {
+                        if let Some((end, replacement)) = (matcher.consume)(bytes, i) {
+                            emit(&mut result, matcher, i..end, replacement);
+                            i = end;
+                            matched = true;
+                            break;
+                        }
+                    }
+                }
+                if matched {
+                    continue;
+                }
+            }
+            // Advance by full UTF-8 character to preserve multibyte sequences.
+            // All pattern start bytes are ASCII, so non-ASCII bytes always land here.
+            let ch_len = utf8_char_width(bytes[i]);
+            result.push_str(&input[i..i + ch_len]);
+            i += ch_len;
+        }
+
+        result
+    }
+
+    /// Single-pattern scanner used by individual normalizer functions.
+    pub(super) fn replace_pattern<P, C>(input: &str, is_start: P, consume: C) -> String
+    where
+        P: Fn(&Scan, usize) -> bool,
+        C: Fn(&Scan, usize) -> Option<(usize, &'static str)>,
+    {
+        let bytes = &Scan::new(input.as_bytes(), false);
+        let len = bytes.len();
+        let mut result = String::with_capacity(len);
+        let mut i = 0;
+
+        while i < len {
+            if bytes[i].is_ascii() && is_start(bytes, i) {
+                if let Some((end, replacement)) = consume(bytes, i) {
+                    result.push_str(replacement);
+                    i = end;
+                    continue;
+                }
+            }
+            let ch_len = utf8_char_width(bytes[i]);
+            result.push_str(&input[i..i + ch_len]);
+            i += ch_len;
+        }
+
+        result
+    }
+
+    fn utf8_char_width(b: u8) -> usize {
+        match b {
+            0..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        }
+    }
+}
+
+/// One submodule per pattern. Each exposes `is_start(bytes, i) -> bool` and
+/// `consume(bytes, i) -> Option<(end, replacement)>` so the engine can drive
+/// them through a uniform [`engine::Matcher`].
+mod patterns {
+    use super::engine::Scan;
+
+    fn is_hex_digit(b: u8) -> bool {
+        b.is_ascii_digit() || (b'a'..=b'f').contains(&b) || (b'A'..=b'F').contains(&b)
+    }
+
+    fn is_word_byte(b: u8) -> bool {
+        b.is_ascii_alphanumeric() || b == b'_'
+    }
+
+    fn is_path_byte(b: u8) -> bool {
+        b.is_ascii_alphanumeric()
+            || matches!(
+                b,
+                b'/' | b'\\' | b'.' | b'_' | b'-' | b'~' | b'+' | b'%' | b'@'
+            )
+    }
+
+    /// `i` does not continue a path. Patterns that can consume bytes a path
+    /// stops at (`:`, spaces, non-ASCII) start only here: otherwise their
+    /// placeholder could join a rejected path prefix to the segments after
+    /// it and form a path on a second pass.
+    fn path_start(bytes: &Scan, i: usize) -> bool {
+        i == 0 || !is_path_byte(bytes[i - 1]) && !placeholder_before(bytes, i)
+    }
+
+    /// `i` does not continue a word.
+    fn word_start(bytes: &Scan, i: usize) -> bool {
+        i == 0 || !is_word_byte(bytes[i - 1]) && !placeholder_before(bytes, i)
+    }
+
+    /// `end` does not split a word.
+    fn word_end(bytes: &Scan, end: usize) -> bool {
+        end == bytes.len() || !is_word_byte(bytes[end]) && !placeholder_at(bytes, end)
+    }
+
+    // Typed placeholders stand in for a word, as the fixed ones (`0`,
+    // `_hex_`) do, so patterns must not start or end next to them either —
+    // in scans passing them through; see `Scan::typed`.
+
+    /// A typed placeholder starts at `i`.
+    fn placeholder_at(bytes: &Scan, i: usize) -> bool {
+        bytes.typed
+            && i < bytes.len()
+            && typed_placeholders::is_start(bytes, i)
+            && typed_placeholders::consume(bytes, i).is_some()
+    }
+
+    /// A typed placeholder ends at `end`.
+    fn placeholder_before(bytes: &Scan, end: usize) -> bool {
+        bytes.typed
+            && end > 0
+            && bytes[end - 1] == b'>'
+            && bytes[..end - 1]
+                .iter()
+                .rposition(|&b| b == b'<')
+                .is_some_and(|i| typed_placeholders::consume(bytes, i).map(|(e, _)| e) == Some(end))
+    }
+
+    /// End of the run of ASCII digits starting at `i`.
+    fn digits_end(bytes: &[u8], i: usize) -> usize {
+        let mut end = i;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        end
+    }
+
+    /// Exactly `n` ASCII digits at `i`.
+    fn has_digits(bytes: &[u8], i: usize, n: usize) -> bool {
+        i + n <= bytes.len() && bytes[i..i + n].iter().all(u8::is_ascii_digit)
+    }
+
+    pub mod uuids {
+        use super::{is_hex_digit, word_end, word_start, Scan};
+
+        const GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            i + 36 <= bytes.len()
+                && is_hex_digit(bytes[i])
+                && bytes[i + 8] == b'-'
+                && word_start(bytes, i)
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut end = i;
+            for (n, &len) in GROUPS.iter().enumerate() {
+                if n > 0 {
+                    if bytes.get(end) != Some(&b'-') {
+                        return None;
+                    }
+                    end += 1;
+                }
+                if end + len > bytes.len()
+                    || !bytes[end..end + len].iter().all(|&b| is_hex_digit(b))
+                {
+                    return None;
+                }
+                end += len;
+            }
+            if word_end(bytes, end) && bytes.get(end) != Some(&b'-') {
+                Some((end, "_uuid_"))
+            } else {
+                None
+            }
+        }
+    }
+
+    pub mod ipv4_addresses {
+        use super::{digits_end, is_word_byte, word_end, Scan};
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            bytes[i].is_ascii_digit()
+                && (i == 0 || !is_word_byte(bytes[i - 1]) && bytes[i - 1] != b'.')
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut end = i;
+            for octet in 0..4 {
+                if octet > 0 {
+                    if bytes.get(end) != Some(&b'.') {
+                        return None;
+                    }
+                    end += 1;
+                }
+                // Octet ranges aren't checked: `numeric_ids` would shrink an
+                // out-of-range octet to `0`, and the result must not match
+                // later.
+                let digits = digits_end(bytes, end);
+                if digits == end {
+                    return None;
+                }
+                end = digits;
+            }
+            let continues =
+                bytes.get(end) == Some(&b'.') && bytes.get(end + 1).is_some_and(u8::is_ascii_digit);
+            if word_end(bytes, end) && !continues {
+                Some((end, "_ip_"))
+            } else {
+                None
+            }
+        }
+    }
+
+    pub mod ipv6_addresses {
+        use super::{is_hex_digit, path_start, word_end, Scan};
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            (is_hex_digit(bytes[i]) || bytes[i] == b':')
+                && path_start(bytes, i)
+                && (i == 0 || bytes[i - 1] != b':')
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut end = i;
+            while end < bytes.len() && (is_hex_digit(bytes[end]) || bytes[end] == b':') {
+                end += 1;
+            }
+            // A single trailing colon is punctuation, not part of the
+            // address, unless a word follows it directly.
+            if end - i > 2 && bytes[end - 1] == b':' && bytes[end - 2] != b':' {
+                if !word_end(bytes, end) {
+                    return None;
+                }
+                end -= 1;
+            }
+            let candidate = &bytes[i..end];
+
+            let compressed = candidate.windows(2).filter(|w| w == b"::").count();
+            let groups: Vec<&[u8]> = candidate.split(|&b| b == b':').collect();
+            let filled = groups.iter().filter(|g| !g.is_empty()).count();
+            let valid = match compressed {
+                0 => groups.len() == 8 && filled == 8,
+                1 => filled <= 7 && !candidate.windows(3).any(|w| w == b":::"),
+                _ => false,
+            };
+
+            // Group lengths aren't checked: `numeric_ids` would shrink an
+            // over-long group to `0`, and the result must not match later.
+            if valid && candidate.iter().any(u8::is_ascii_digit) && word_end(bytes, end) {
+                Some((end, "_ip_"))
+            } else {
+                None
+            }
+        }
+    }
+
+    pub mod emails {
+        use super::{is_word_byte, placeholder_at, word_end, Scan};
+
+        fn is_local_byte(b: u8) -> bool {
+            b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'%' | b'+' | b'-')
+        }
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            bytes[i].is_ascii_alphanumeric() && (i == 0 || !is_local_byte(bytes[i - 1]))
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut at = i;
+            while at < bytes.len() && is_local_byte(bytes[at]) {
+                at += 1;
+            }
+            if bytes.get(at) != Some(&b'@') {
+                return None;
+            }
+
+            // Domain: two or more dot-separated labels, the last alphabetic.
+            let mut end = at + 1;
+            let mut labels = 0;
+            let mut last_alpha;
+            loop {
+                let start = end;
+                while end < bytes.len()
+                    && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'-')
+                {
+                    end += 1;
+                }
+                if end == start {
+                    return None;
+                }
+                labels += 1;
+                last_alpha =
+                    end - start >= 2 && bytes[start..end].iter().all(u8::is_ascii_alphabetic);
+                if bytes.get(end) != Some(&b'.') {
+                    break;
+                }
+                match bytes.get(end + 1) {
+                    Some(b) if b.is_ascii_alphanumeric() => {}
+                    // The domain continues in a way we can't parse (`._x`),
+                    // or with a label already replaced.
+                    Some(&b) if is_word_byte(b) || b == b'-' => return None,
+                    _ if placeholder_at(bytes, end + 1) => return None,
+                    _ => break,
+                }
+                end += 1;
+            }
+
+            if labels >= 2 && last_alpha && word_end(bytes, end) {
+                Some((end, "_email_"))
+            } else {
+                None
+            }
+        }
+    }
+
+    pub mod urls {
+        use super::{word_start, Scan};
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            bytes[i].is_ascii_alphabetic() && word_start(bytes, i)
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut scheme = i;
+            while scheme < bytes.len()
+                && (bytes[scheme].is_ascii_alphanumeric()
+                    || matches!(bytes[scheme], b'+' | b'.' | b'-'))
+            {
+                scheme += 1;
+            }
+            if !bytes[scheme..].starts_with(b"://") {
+                return None;
+            }
+
+            let rest = scheme + 3;
+            let mut end = rest;
+            while end < bytes.len()
+                && bytes[end].is_ascii_graphic()
+                && !matches!(bytes[end], b'"' | b'\'' | b'<' | b'>' | b'`')
+            {
+                end += 1;
+            }
+            // Sentence punctuation and closing brackets trailing a URL are
+            // almost never part of it.
+            while end > rest
+                && matches!(
+                    bytes[end - 1],
+                    b'.' | b',' | b';' | b':' | b'!' | b'?' | b')' | b']' | b'}'
+                )
+            {
+                end -= 1;
+            }
+            if end > rest {
+                Some((end, "_url_"))
+            } else {
+                None
+            }
+        }
+    }
+
+    pub mod paths {
+        use super::{is_path_byte, Scan};
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            let rooted = match bytes[i] {
+                b'/' => true,
+                b'~' => bytes.get(i + 1) == Some(&b'/'),
+                b if b.is_ascii_alphabetic() => {
+                    bytes.get(i + 1) == Some(&b':') && bytes.get(i + 2) == Some(&b'\\')
+                }
+                _ => false,
+            };
+            rooted
+                && (i == 0
+                    || bytes[i - 1].is_ascii_whitespace()
+                    || matches!(
+                        bytes[i - 1],
+                        b'"' | b'\'' | b'(' | b'[' | b'<' | b'=' | b','
+                    ))
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            // Skip a drive letter's colon, which is not a path byte.
+            let mut end = if bytes.get(i + 1) == Some(&b':') {
+                i + 2
+            } else {
+                i
+            };
+            while end < bytes.len() && is_path_byte(bytes[end]) {
+                end += 1;
+            }
+            while end > i && bytes[end - 1] == b'.' {
+                end -= 1;
+            }
+            let separators = bytes[i..end]
+                .iter()
+                .filter(|&&b| b == b'/' || b == b'\\')
+                .count();
+            // Two or more segments: `/a/b` or `/a/b/`, but not `/a/`.
+            let trailing = matches!(bytes[end - 1], b'/' | b'\\');
+            if separators >= 3 || separators == 2 && !trailing {
+                Some((end, "_path_"))
+            } else {
+                None
+            }
+        }
+    }
 
-    /// Single-pass scanner that tries multiple pattern matchers at each byte
-    /// position. First matching pattern wins. One allocation, one scan.
-    pub(super) fn replace_patterns(input: &str, matchers: &[Matcher]) -> String {
-        let bytes = input.as_bytes();
-        let len = bytes.len();
-        let mut result = String::with_capacity(len);
-        let mut i = 0;
+    pub mod durations {
+        use super::{digits_end, path_start, word_end, Scan};
 
-        while i < len {
-            if bytes[i].is_ascii() {
-                let mut matched = false;
-                for (is_start, consume) in matchers {
-                    if is_start(bytes, i) {
-                        if let Some((end, replacement)) = consume(bytes, i) {
-                            result.push_str(replacement);
-                            i = end;
-                            matched = true;
-                            break;
-                        }
-                    }
+        /// Longest first, so `ms` and `min` win over `m`.
+        const UNITS: &[&[u8]] = &[
+            b"min",
+            b"ms",
+            b"us",
+            "\u{b5}s".as_bytes(),
+            b"ns",
+            b"h",
+            b"m",
+            b"s",
+            b"d",
+        ];
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            bytes[i].is_ascii_digit() && path_start(bytes, i)
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut end = i;
+            // One or more `<number><unit>` components, as in `1h30m`.
+            loop {
+                let mut number = digits_end(bytes, end);
+                if number == end {
+                    break;
                 }
-                if matched {
-                    continue;
+                if bytes.get(number) == Some(&b'.')
+                    && bytes.get(number + 1).is_some_and(u8::is_ascii_digit)
+                {
+                    number = digits_end(bytes, number + 1);
+                }
+                match UNITS.iter().find(|unit| bytes[number..].starts_with(unit)) {
+                    Some(unit) => end = number + unit.len(),
+                    None => break,
                 }
             }
-            // Advance by full UTF-8 character to preserve multibyte sequences.
-            // All pattern start bytes are ASCII, so non-ASCII bytes always land here.
-            let ch_len = utf8_char_width(bytes[i]);
-            result.push_str(&input[i..i + ch_len]);
-            i += ch_len;
+            if end > i && word_end(bytes, end) {
+                Some((end, "_duration_"))
+            } else {
+                None
+            }
         }
-
-        result
     }
 
-    /// Single-pattern scanner used by individual normalizer functions.
-    pub(super) fn replace_pattern<P, C>(input: &str, is_start: P, consume: C) -> String
-    where
-        P: Fn(&[u8], usize) -> bool,
-        C: Fn(&[u8], usize) -> Option<(usize, &'static str)>,
-    {
-        let bytes = input.as_bytes();
-        let len = bytes.len();
-        let mut result = String::with_capacity(len);
-        let mut i = 0;
+    pub mod iso_timestamps {
+        use super::{digits_end, has_digits, path_start, word_end, Scan};
 
-        while i < len {
-            if bytes[i].is_ascii() && is_start(bytes, i) {
-                if let Some((end, replacement)) = consume(bytes, i) {
-                    result.push_str(replacement);
-                    i = end;
-                    continue;
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            has_digits(bytes, i, 4) && bytes.get(i + 4) == Some(&b'-') && path_start(bytes, i)
+        }
+
+        /// `HH:MM[:SS[.frac]]` at `i`, returning its end.
+        fn time(bytes: &[u8], i: usize) -> Option<usize> {
+            if !(has_digits(bytes, i, 2)
+                && bytes.get(i + 2) == Some(&b':')
+                && has_digits(bytes, i + 3, 2))
+            {
+                return None;
+            }
+            let mut end = i + 5;
+            if bytes.get(end) == Some(&b':') && has_digits(bytes, end + 1, 2) {
+                end += 3;
+                if matches!(bytes.get(end), Some(b'.') | Some(b','))
+                    && bytes.get(end + 1).is_some_and(u8::is_ascii_digit)
+                {
+                    end = digits_end(bytes, end + 1);
                 }
             }
-            let ch_len = utf8_char_width(bytes[i]);
-            result.push_str(&input[i..i + ch_len]);
-            i += ch_len;
+            Some(end)
         }
 
-        result
-    }
-
-    fn utf8_char_width(b: u8) -> usize {
-        match b {
-            0..=0x7F => 1,
-            0xC0..=0xDF => 2,
-            0xE0..=0xEF => 3,
-            0xF0..=0xF7 => 4,
-            _ => 1,
+        /// `Z`, `±HH`, `±HHMM` or `±HH:MM` at `i`, optionally after one space.
+        fn zone(bytes: &[u8], i: usize) -> Option<usize> {
+            if bytes.get(i) == Some(&b'Z') {
+                return Some(i + 1);
+            }
+            let sign = if bytes.get(i) == Some(&b' ') {
+                i + 1
+            } else {
+                i
+            };
+            if !matches!(bytes.get(sign), Some(b'+') | Some(b'-'))
+                || !has_digits(bytes, sign + 1, 2)
+            {
+                return None;
+            }
+            let hours = sign + 3;
+            if has_digits(bytes, hours, 2) {
+                Some(hours + 2)
+            } else if bytes.get(hours) == Some(&b':') && has_digits(bytes, hours + 1, 2) {
+                Some(hours + 3)
+            } else {
+                Some(hours)
+            }
         }
-    }
-}
 
-/// One submodule per pattern. Each exposes `is_start(bytes, i) -> bool` and
-/// `consume(bytes, i) -> Option<(end, replacement)>` so the engine can drive
-/// them through a uniform [`engine::Matcher`] tuple.
-mod patterns {
-    fn is_hex_digit(b: u8) -> bool {
-        b.is_ascii_digit() || (b'a'..=b'f').contains(&b) || (b'A'..=b'F').contains(&b)
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let date = has_digits(bytes, i + 5, 2)
+                && bytes.get(i + 7) == Some(&b'-')
+                && has_digits(bytes, i + 8, 2);
+            if !date {
+                return None;
+            }
+            let mut end = i + 10;
+            if matches!(bytes.get(end), Some(b'T') | Some(b' ')) {
+                if let Some(t) = time(bytes, end + 1) {
+                    end = t;
+                    if let Some(z) = zone(bytes, end) {
+                        if word_end(bytes, z) {
+                            end = z;
+                        }
+                    }
+                }
+            }
+            if word_end(bytes, end) {
+                Some((end, "_timestamp_"))
+            } else {
+                None
+            }
+        }
     }
 
     pub mod hex_addresses {
-        use super::is_hex_digit;
+        use super::{is_hex_digit, Scan};
 
-        pub fn is_start(bytes: &[u8], i: usize) -> bool {
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
             i + 2 < bytes.len()
                 && bytes[i] == b'0'
                 && bytes[i + 1] == b'x'
                 && is_hex_digit(bytes[i + 2])
         }
 
-        pub fn consume(bytes: &[u8], i: usize) -> Option<(usize, &'static str)> {
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
             let mut end = i + 2;
             while end < bytes.len() && is_hex_digit(bytes[end]) {
                 end += 1;
@@ -243,9 +1640,9 @@ mod patterns {
     }
 
     pub mod hex_tokens {
-        use super::is_hex_digit;
+        use super::{is_hex_digit, Scan};
 
-        pub fn is_start(bytes: &[u8], i: usize) -> bool {
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
             if !is_hex_digit(bytes[i]) {
                 return false;
             }
@@ -261,7 +1658,7 @@ mod patterns {
             true
         }
 
-        pub fn consume(bytes: &[u8], i: usize) -> Option<(usize, &'static str)> {
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
             let mut end = i;
             let mut has_alpha_hex = false;
             while end < bytes.len() && is_hex_digit(bytes[end]) {
@@ -287,12 +1684,15 @@ mod patterns {
     }
 
     pub mod numeric_ids {
-        pub fn is_start(bytes: &[u8], i: usize) -> bool {
+        use super::Scan;
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
             bytes[i].is_ascii_digit()
                 && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric() && bytes[i - 1] != b'_')
+                && !super::placeholder_before(bytes, i)
         }
 
-        pub fn consume(bytes: &[u8], i: usize) -> Option<(usize, &'static str)> {
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
             let mut end = i;
             while end < bytes.len() && bytes[end].is_ascii_digit() {
                 end += 1;
@@ -309,8 +1709,51 @@ mod patterns {
         }
     }
 
+    /// Typed placeholders (`<num:6>`, `<url:ht>`) with a known tag.
+    pub mod typed_placeholders {
+        use super::Scan;
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
+            bytes[i] == b'<'
+        }
+
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
+            let mut end = i + 1;
+            while end < bytes.len() && bytes[end].is_ascii_alphanumeric() {
+                end += 1;
+            }
+            let tag = &bytes[i + 1..end];
+            if !crate::normalize::DEFAULT_MATCHERS
+                .iter()
+                .any(|m| m.tag.as_bytes() == tag)
+            {
+                return None;
+            }
+            if bytes.get(end) == Some(&b':') {
+                let start = end + 1;
+                end = start;
+                while end < bytes.len()
+                    && !bytes[end].is_ascii_whitespace()
+                    && !matches!(bytes[end], b'<' | b'>')
+                {
+                    end += 1;
+                }
+                if end == start {
+                    return None;
+                }
+            }
+            if bytes.get(end) == Some(&b'>') {
+                Some((end + 1, ""))
+            } else {
+                None
+            }
+        }
+    }
+
     pub mod timestamps {
-        pub fn is_start(bytes: &[u8], i: usize) -> bool {
+        use super::Scan;
+
+        pub fn is_start(bytes: &Scan, i: usize) -> bool {
             i + 6 < bytes.len()
                 && bytes[i] == b'"'
                 && bytes[i + 1].is_ascii_digit()
@@ -320,7 +1763,7 @@ mod patterns {
                 && bytes[i + 5] == b'-'
         }
 
-        pub fn consume(bytes: &[u8], i: usize) -> Option<(usize, &'static str)> {
+        pub fn consume(bytes: &Scan, i: usize) -> Option<(usize, &'static str)> {
             let mut end = i + 1;
             while end < bytes.len() && bytes[end] != b'"' {
                 end += 1;
@@ -412,6 +1855,160 @@ mod tests {
         );
     }
 
+    #[test]
+    fn uuids_replaces_dashed_uuids() {
+        assert_eq!(
+            uuids("request 123e4567-E89B-12d3-a456-426614174000 failed"),
+            "request _uuid_ failed"
+        );
+        assert_eq!(
+            uuids("short 123e4567-e89b-12d3-a456-42661417 end"),
+            "short 123e4567-e89b-12d3-a456-42661417 end"
+        );
+    }
+
+    #[test]
+    fn ipv4_addresses_replaces_dotted_quads() {
+        assert_eq!(
+            ipv4_addresses("from 10.0.12.7:8080 via 192.168.1.1."),
+            "from _ip_:8080 via _ip_."
+        );
+        assert_eq!(
+            ipv4_addresses("version 1.2.3.4.5 and v1.2.3.4"),
+            "version 1.2.3.4.5 and v1.2.3.4"
+        );
+    }
+
+    #[test]
+    fn ipv6_addresses_requires_compression_or_eight_groups() {
+        assert_eq!(
+            ipv6_addresses("peer fe80::1ff:fe23:4567:890a and ::1 and 2001:db8:0:0:0:0:2:1"),
+            "peer _ip_ and _ip_ and _ip_"
+        );
+        assert_eq!(
+            ipv6_addresses("at 10:30:00 in Shop::Cafe"),
+            "at 10:30:00 in Shop::Cafe"
+        );
+    }
+
+    #[test]
+    fn emails_replaces_addresses() {
+        assert_eq!(
+            emails("sent to jane.doe+orders@mail.example.com, bob@x"),
+            "sent to _email_, bob@x"
+        );
+    }
+
+    #[test]
+    fn urls_replaces_whole_url_with_query_string() {
+        assert_eq!(
+            urls("GET https://shop.example.com/cart?id=42&ref=abc). retry"),
+            "GET _url_). retry"
+        );
+    }
+
+    #[test]
+    fn paths_replaces_multi_segment_paths() {
+        assert_eq!(
+            paths("open /var/log/app/42/out.log: denied in ~/src/app and C:\\Users\\jane"),
+            "open _path_: denied in _path_ and _path_"
+        );
+        assert_eq!(
+            paths("GET /health HTTP/1.1 and a/b/c"),
+            "GET /health HTTP/1.1 and a/b/c"
+        );
+    }
+
+    #[test]
+    fn durations_replaces_numbers_with_time_units() {
+        assert_eq!(
+            durations("took 123ms (1.5s, 1h30m, 5min) on 3 tries of 4kb"),
+            "took _duration_ (_duration_, _duration_, _duration_) on 3 tries of 4kb"
+        );
+    }
+
+    #[test]
+    fn iso_timestamps_replaces_unquoted_timestamps() {
+        assert_eq!(
+            iso_timestamps(
+                "at 2026-03-25T03:56:10.370Z, 2026-03-25 03:56:10 +0000 and 2026-03-25."
+            ),
+            "at _timestamp_, _timestamp_ and _timestamp_."
+        );
+    }
+
+    #[test]
+    fn default_normalizer_handles_log_line_values() {
+        let norm = default_normalizer();
+        assert_eq!(
+            norm("2026-03-25T03:56:10Z user jane@example.com from 10.0.0.7 GET https://a.example/x?id=9 req 123e4567-e89b-12d3-a456-426614174000 took 41ms"),
+            "_timestamp_ user _email_ from _ip_ GET _url_ req _uuid_ took _duration_"
+        );
+    }
+
+    #[test]
+    fn named_fuses_patterns_in_priority_order_then_transforms() {
+        let norm = named("numeric, whitespace,hex").unwrap();
+        assert_eq!(
+            norm("  object   3966ad2c7d6a51b5 id 42 "),
+            "object _hex_ id 0"
+        );
+    }
+
+    #[test]
+    fn named_presets_expand() {
+        let input = "Error at 0x00007f3a1b2c3d48  from jane@example.com";
+        assert_eq!(
+            named("default").unwrap()(input),
+            default_normalizer()(input)
+        );
+        assert_eq!(named("logs").unwrap()(input), "Error at 0x_ from _email_");
+        assert_eq!(named("none").unwrap()(input), input);
+        assert_eq!(
+            named("merchants").unwrap()("TARGET   Store 1244 "),
+            "target store 0"
+        );
+        // Punctuation is stripped, so the store number's `#` goes too.
+        assert_eq!(
+            named("merchants").unwrap()("TARGET   Store #1244 "),
+            "target store 0"
+        );
+    }
+
+    #[test]
+    fn named_rejects_unknown_names() {
+        let err = named("hex,bogus").err().unwrap();
+        assert!(
+            err.contains("'bogus'") && err.contains("stacktraces"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn canonicalization_normalizers() {
+        assert_eq!(
+            strip_punctuation("McDonald\u{2019}s, Target.com!"),
+            "McDonalds  Target com "
+        );
+        assert_eq!(
+            remove_diacritics("José Ångström café"),
+            "Jose Angstrom cafe"
+        );
+        assert_eq!(nfkc("\u{fb01}ne \u{2460}"), "fine 1");
+        assert_eq!(fullwidth_to_ascii("ＴＡＲＧＥＴ　１２"), "TARGET 12");
+    }
+
+    #[test]
+    fn entity_normalizer_collapses_name_variants() {
+        let norm = entity_normalizer();
+        assert_eq!(norm("TARGET "), norm("Target"));
+        assert_eq!(norm("Ｔａｒｇｅｔ"), "target");
+        assert_eq!(norm("José's  Café"), norm("jose's cafe"));
+        assert_eq!(norm("José's  Café"), "joses cafe");
+        assert_eq!(norm(&norm("\u{130}stanbul")), norm("\u{130}stanbul"));
+    }
+
     #[test]
     fn compose_chains_normalizers() {
         let norm = compose(hex_addresses, numeric_ids);
@@ -429,6 +2026,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn typed_placeholders_encode_shape() {
+        let norm = default_normalizer_with(Placeholders::Typed { keep_prefix: 0 });
+        let input = "Widget:0x00007f3a1b2c3d48 @parent_id=1234, token deadbeef0123 at \"2026-03-25 03:56:10 +0000\" from 10.0.0.7 in 15ms";
+        assert_eq!(
+            norm(input),
+            "Widget:0x<hex:16> @parent_id=<num:4>, token <hex:12> at \"<ts>\" from <ipv4> in <duration>"
+        );
+        assert_ne!(norm("store 042"), norm("store 918273645012"));
+        assert_eq!(norm(&norm(input)), norm(input));
+    }
+
+    #[test]
+    fn fixed_placeholders_ignore_typed_placeholder_text() {
+        // Typed placeholders in the input are plain text to fixed scans, as
+        // they were before typed placeholders existed.
+        let norm = default_normalizer();
+        assert_eq!(norm("<num:3>5 x"), "<num:0>0 x");
+        assert_eq!(norm("id <num:3> 42"), "id <num:0> 0");
+        assert_eq!(norm("<ts>2026-01-01"), "<ts>_timestamp_");
+        assert_eq!(norm("<uuid>12345"), "<uuid>0");
+        assert_eq!(
+            capture_patterns("<num:3>5", Placeholders::Fixed).0,
+            "<num:0>0"
+        );
+
+        // Typed scans keep them whole and don't start a match against them.
+        let typed = default_normalizer_with(Placeholders::Typed { keep_prefix: 0 });
+        assert_eq!(typed("<num:3>5 x"), "<num:3>5 x");
+    }
+
+    #[test]
+    fn typed_placeholders_keep_prefix() {
+        let norm = default_normalizer_with(Placeholders::Typed { keep_prefix: 3 });
+        assert_eq!(
+            norm("MCD 105109 via https://x.example/a, at \"2026-03-25 03:56\""),
+            "MCD <num:6:105> via <url:htt>, at \"<ts:202>\""
+        );
+        assert_eq!(norm("<num:6:105>"), "<num:6:105>");
+        assert_eq!(
+            numeric_ids_with(Placeholders::Typed { keep_prefix: 1 })("id 42 0x1f2e3d"),
+            "id <num:2:4> 0x1f2e3d"
+        );
+        let named = named_with("hex", Placeholders::Typed { keep_prefix: 0 }).unwrap();
+        assert_eq!(named("0x1f2e3d id 42"), "0x<hex:6> id 42");
+    }
+
+    #[test]
+    fn capture_patterns_reports_replaced_values() {
+        let input = "GET https://shop.example/o/77 for 4821 from 10.0.0.7";
+        let (key, captures) = capture_patterns(input, Placeholders::Typed { keep_prefix: 0 });
+        assert_eq!(key, "GET <url> for <num:4> from <ipv4>");
+        let found: Vec<(&str, &str, &str)> = captures
+            .iter()
+            .map(|c| (c.pattern, &input[c.span.clone()], c.replacement.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("urls", "https://shop.example/o/77", "<url>"),
+                ("numeric_ids", "4821", "<num:4>"),
+                ("ipv4_addresses", "10.0.0.7", "<ipv4>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracted_values_are_distinct_per_pattern() {
+        let values = extracted_values(&["order 12 in 5ms", "order 9 in 5ms", "order 12 in 7ms"]);
+        assert_eq!(values["numeric_ids"], vec!["12", "9"]);
+        assert_eq!(values["durations"], vec!["5ms", "7ms"]);
+    }
+
+    #[test]
+    fn value_histograms_follow_placeholder_positions() {
+        let records = [
+            "#<Widget:0x00ff12 @parent_id=12, created_at: \"2026-03-25 03:56:10\">",
+            "#<Widget:0x00ff13 @parent_id=12, created_at: \"2026-03-24 01:00:00\">",
+            "#<Widget:0x00ff14 @parent_id=7, created_at: \"2026-03-26 09:30:00\">",
+        ];
+        let histograms = value_histograms(&records);
+        let fields: Vec<(usize, &str, &str, usize)> = histograms
+            .iter()
+            .map(|h| (h.position, h.pattern, h.label.as_str(), h.counts.len()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (0, "hex_addresses", "Widget", 3),
+                (1, "numeric_ids", "parent_id", 2),
+                (2, "timestamps", "created_at", 3),
+            ]
+        );
+        assert_eq!(histograms[1].counts[0], ("12".to_string(), 2));
+        assert_eq!(histograms[1].total(), 3);
+        assert_eq!(
+            histograms[2].range(),
+            Some(("\"2026-03-24 01:00:00\"", "\"2026-03-26 09:30:00\""))
+        );
+    }
+
     #[test]
     fn default_normalizer_is_identity_on_fqcn() {
         let norm = default_normalizer();
@@ -436,6 +2134,54 @@ mod tests {
         assert_eq!(norm(input), input);
     }
 
+    #[test]
+    fn rules_run_before_builtin_patterns() {
+        let rules = Rules::from_toml(
+            r#"
+            [[rule]]
+            name = "tenant"
+            pattern = "tenant-[a-z0-9]+"
+            replacement = "<tenant>"
+
+            [[rule]]
+            name = "request"
+            pattern = "req_[A-Za-z0-9]+"
+            replacement = "<req>"
+            "#,
+        )
+        .unwrap();
+        let norm = rules_normalizer(rules);
+        assert_eq!(
+            norm("tenant-acme42 req_9fK2 failed after 3 retries at 0x00007f3a1b2c3d48"),
+            "<tenant> <req> failed after 0 retries at 0x_"
+        );
+    }
+
+    #[test]
+    fn rules_priority_breaks_ties_at_same_position() {
+        let rule = |name: &str, pattern: &str, priority| Rule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            replacement: format!("<{}>", name),
+            priority,
+        };
+        let rules = Rules::new(
+            vec![rule("word", "[a-z]+", 0), rule("user", "user[0-9]+", 5)],
+            false,
+        )
+        .unwrap();
+        assert_eq!(rules_normalizer(rules)("user42 left 7"), "<user> <word> 7");
+    }
+
+    #[test]
+    fn rules_report_invalid_patterns_by_name() {
+        let err = Rules::from_toml(
+            "defaults = false\n[[rule]]\nname = \"broken\"\npattern = \"(\"\nreplacement = \"x\"\n",
+        )
+        .unwrap_err();
+        assert!(err.starts_with("rule 'broken'"), "{}", err);
+    }
+
     use quickcheck_macros::quickcheck;
 
     #[quickcheck]
@@ -479,6 +2225,50 @@ mod tests {
         once == twice
     }
 
+    #[quickcheck]
+    fn prop_default_normalizer_idempotent_on_pattern_alphabet(bytes: Vec<u8>) -> bool {
+        // Arbitrary strings rarely contain the shapes the patterns look for;
+        // drawing from their alphabet exercises the interactions between them.
+        const ALPHABET: &[u8] = b"0123456789abcdefhmnpstxTZ.:-/@ ~\\\"(";
+        let s: String = bytes
+            .iter()
+            .map(|&b| ALPHABET[b as usize % ALPHABET.len()] as char)
+            .collect();
+        let norm = default_normalizer();
+        let once = norm(&s);
+        let twice = norm(&once);
+        once == twice
+    }
+
+    #[quickcheck]
+    fn prop_typed_placeholders_idempotent(bytes: Vec<u8>, keep_prefix: u8) -> bool {
+        const ALPHABET: &[u8] = b"0123456789abcdefhmnpstuxTZ.:-/@ ~\\\"(<>";
+        let s: String = bytes
+            .iter()
+            .map(|&b| ALPHABET[b as usize % ALPHABET.len()] as char)
+            .collect();
+        let norm = default_normalizer_with(Placeholders::Typed {
+            keep_prefix: keep_prefix as usize % 4,
+        });
+        let once = norm(&s);
+        let twice = norm(&once);
+        once == twice
+    }
+
+    #[quickcheck]
+    fn prop_capture_patterns_matches_default_normalizer(s: String) -> bool {
+        let (key, captures) = capture_patterns(&s, Placeholders::Fixed);
+        let mut rebuilt = String::new();
+        let mut last = 0;
+        for capture in &captures {
+            rebuilt.push_str(&s[last..capture.span.start]);
+            rebuilt.push_str(&capture.replacement);
+            last = capture.span.end;
+        }
+        rebuilt.push_str(&s[last..]);
+        key == default_normalizer()(&s) && rebuilt == key
+    }
+
     #[quickcheck]
     fn prop_normalizers_produce_valid_utf8(s: String) -> bool {
         // Each normalizer should return a valid String (enforced by type system),
@@ -487,10 +2277,31 @@ mod tests {
         let _ = numeric_ids(&s);
         let _ = timestamps(&s);
         let _ = hex_tokens(&s);
+        let _ = uuids(&s);
+        let _ = ipv4_addresses(&s);
+        let _ = ipv6_addresses(&s);
+        let _ = emails(&s);
+        let _ = urls(&s);
+        let _ = paths(&s);
+        let _ = durations(&s);
+        let _ = iso_timestamps(&s);
         let _ = default_normalizer()(&s);
         true
     }
 
+    #[quickcheck]
+    fn prop_rules_without_rules_match_default_normalizer(s: String) -> bool {
+        let rules = rules_normalizer(Rules::new(Vec::new(), true).unwrap());
+        rules(&s) == default_normalizer()(&s)
+    }
+
+    #[quickcheck]
+    fn prop_entity_normalizer_idempotent(s: String) -> bool {
+        let norm = entity_normalizer();
+        let once = norm(&s);
+        norm(&once) == once
+    }
+
     #[quickcheck]
     fn prop_compose_matches_sequential_application(s: String) -> bool {
         let composed = compose(hex_addresses, numeric_ids);
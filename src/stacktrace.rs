@@ -0,0 +1,298 @@
+//! Comparing stack traces by their frames.
+//!
+//! [`parse_frames`] reads Java (`at pkg.Class.method(File.java:42)`), Ruby
+//! (`app/cart.rb:12:in 'total'`) and Python (`File "app.py", line 3, in
+//! handler`) frames out of a multi-line record, dropping line numbers so a
+//! trace survives unrelated edits to the files it passes through. The
+//! [`Config::stack_trace`](crate::Config::stack_trace) metric then scores two
+//! traces by their frames alone, weighting the innermost ones most: two
+//! errors thrown from the same place are the same problem whatever their
+//! messages say.
+
+use crate::normalize::{self, Normalizer};
+use crate::Distance;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// One stack frame, without its line number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Frame {
+    /// Method or function, e.g. `com.shop.Cart.total`, `total`, `handler`.
+    pub function: String,
+    /// File the frame is in, e.g. `Cart.java`, `app/models/cart.rb`.
+    pub file: String,
+}
+
+/// The frames in `trace`, innermost first.
+///
+/// Java and Ruby traces already list the innermost frame first; Python's
+/// "most recent call last" tracebacks are reversed to match. Lines that
+/// aren't frames — messages, `Caused by:`, `... 12 more` — are skipped.
+///
+/// ```
+/// use group_similar::parse_frames;
+///
+/// let frames = parse_frames(
+///     "java.lang.NullPointerException\n\tat com.shop.Cart.total(Cart.java:42)\n\tat com.shop.Checkout.run(Checkout.java:17)",
+/// );
+/// assert_eq!(frames.len(), 2);
+/// assert_eq!(frames[0].function, "com.shop.Cart.total");
+/// assert_eq!(frames[0].file, "Cart.java");
+/// ```
+pub fn parse_frames(trace: &str) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    let mut python = false;
+    for line in trace.lines().map(str::trim) {
+        if let Some(frame) = java_frame(line).or_else(|| ruby_frame(line)) {
+            frames.push(frame);
+        } else if let Some(frame) = python_frame(line) {
+            python = true;
+            frames.push(frame);
+        }
+    }
+    if python {
+        frames.reverse();
+    }
+    frames
+}
+
+/// `at pkg.Class.method(File.java:42)`, `at pkg.Class.method(Native Method)`.
+fn java_frame(line: &str) -> Option<Frame> {
+    let rest = line.strip_prefix("at ")?.strip_suffix(')')?;
+    let (function, file) = rest.split_once('(')?;
+    Some(Frame {
+        function: function.trim().to_string(),
+        file: strip_line_number(file).to_string(),
+    })
+}
+
+/// `from app/cart.rb:12:in 'total'`, or the first line of a Ruby trace,
+/// `app/cart.rb:12:in `total': message`.
+fn ruby_frame(line: &str) -> Option<Frame> {
+    let line = line.strip_prefix("from ").unwrap_or(line);
+    let (location, method) = line.split_once(":in ")?;
+    let file = strip_line_number(location);
+    if file.len() == location.len() || file.is_empty() {
+        return None;
+    }
+    let method = method.strip_prefix(|c| c == '`' || c == '\'')?;
+    let end = method.find('\'')?;
+    Some(Frame {
+        function: method[..end].to_string(),
+        file: file.to_string(),
+    })
+}
+
+/// `File "app/handlers.py", line 3, in handler`.
+fn python_frame(line: &str) -> Option<Frame> {
+    let rest = line.strip_prefix("File \"")?;
+    let (file, rest) = rest.split_once('"')?;
+    let (_, function) = rest.split_once(", in ")?;
+    Some(Frame {
+        function: function.trim().to_string(),
+        file: file.to_string(),
+    })
+}
+
+/// `location` without a trailing `:<digits>`, or unchanged.
+fn strip_line_number(location: &str) -> &str {
+    match location.rsplit_once(':') {
+        Some((file, line)) if !line.is_empty() && line.bytes().all(|b| b.is_ascii_digit()) => file,
+        _ => location,
+    }
+}
+
+/// Position-weighted overlap of two frame sequences, as a distance.
+///
+/// The frame at position `i` (innermost first) weighs `1 / (1 + i)`, and the
+/// similarity is the weighted Jaccard index of the two sequences: per
+/// distinct frame, the smaller of its weights in either trace over the
+/// larger. Identical traces are 0 apart; traces sharing only outer frames
+/// (`main`, the web framework's dispatch) stay far apart.
+pub(crate) fn frame_distance(a: &[Frame], b: &[Frame]) -> Distance {
+    let (wa, wb) = (weights(a), weights(b));
+
+    let mut shared = 0.0;
+    let mut total = 0.0;
+    for (frame, &x) in &wa {
+        let y = wb.get(frame).copied().unwrap_or(0.0);
+        shared += x.min(y);
+        total += x.max(y);
+    }
+    total += wb
+        .iter()
+        .filter(|(frame, _)| !wa.contains_key(*frame))
+        .map(|(_, &y)| y)
+        .sum::<f32>();
+
+    if total == 0.0 {
+        Distance::MIN
+    } else {
+        Distance::clamped(1.0 - shared / total)
+    }
+}
+
+/// Each distinct frame's summed position weight.
+fn weights(frames: &[Frame]) -> HashMap<&Frame, f32> {
+    let mut weights = HashMap::new();
+    for (i, frame) in frames.iter().enumerate() {
+        *weights.entry(frame).or_insert(0.0) += 1.0 / (1.0 + i as f32);
+    }
+    weights
+}
+
+/// Frames as the [`Config::stack_trace`](crate::Config::stack_trace)
+/// metric compares them: parsed and cleaned up with the `stacktraces`
+/// normalizer preset, so hex addresses and generated ids in frame names
+/// (`lambda$12`, `block (2 levels)`) don't tell frames apart. Clustering
+/// parses each representative once through this instead of on every
+/// comparison.
+#[derive(Clone)]
+pub(crate) struct FrameParser {
+    cleanup: Arc<Normalizer>,
+}
+
+impl FrameParser {
+    pub(crate) fn new() -> Self {
+        Self {
+            cleanup: Arc::new(normalize::named("stacktraces").expect("built-in preset")),
+        }
+    }
+
+    /// The cleaned frames of `trace`, innermost first.
+    pub(crate) fn frames(&self, trace: &str) -> Vec<Frame> {
+        parse_frames(trace)
+            .into_iter()
+            .map(|f| Frame {
+                function: (self.cleanup)(&f.function),
+                file: (self.cleanup)(&f.file),
+            })
+            .collect()
+    }
+
+    /// Blocking key of `trace`: its innermost cleaned frame, or empty when
+    /// it has none. Traces thrown from different places are far apart
+    /// whatever their messages say, so they needn't be compared.
+    pub(crate) fn innermost(&self, trace: &str) -> String {
+        self.frames(trace)
+            .first()
+            .map(|f| format!("{}\t{}", f.function, f.file))
+            .unwrap_or_default()
+    }
+}
+
+/// Distance between traces `a` and `b` given their cleaned frames. Records
+/// with no frames are compared with Jaro-Winkler, and never match a record
+/// that has frames.
+pub(crate) fn trace_distance(a: &str, fa: &[Frame], b: &str, fb: &[Frame]) -> Distance {
+    match (fa.is_empty(), fb.is_empty()) {
+        (true, true) => Distance::clamped(1.0 - jaro_winkler::jaro_winkler(a, b)),
+        (true, false) | (false, true) => Distance::MAX,
+        (false, false) => frame_distance(fa, fb),
+    }
+}
+
+/// The [`Config::stack_trace`](crate::Config::stack_trace) metric over raw
+/// records; see [`FrameParser`] and [`trace_distance`].
+pub(crate) fn stack_trace_metric(
+    parser: FrameParser,
+) -> impl Fn(&str, &str) -> Distance + Send + Sync {
+    move |a, b| trace_distance(a, &parser.frames(a), b, &parser.frames(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn frame(function: &str, file: &str) -> Frame {
+        Frame {
+            function: function.to_string(),
+            file: file.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_java_ruby_and_python_frames() {
+        let ruby = "app/models/cart.rb:12:in `total': undefined method 'price' for nil (NoMethodError)\n\tfrom app/controllers/checkout_controller.rb:8:in 'CheckoutController#create'\n\tfrom bin/rails:4:in '<main>'";
+        assert_eq!(
+            parse_frames(ruby),
+            vec![
+                frame("total", "app/models/cart.rb"),
+                frame(
+                    "CheckoutController#create",
+                    "app/controllers/checkout_controller.rb"
+                ),
+                frame("<main>", "bin/rails"),
+            ]
+        );
+
+        let python = "Traceback (most recent call last):\n  File \"app/main.py\", line 40, in <module>\n    main()\n  File \"app/handlers.py\", line 3, in handler\n    return order['id']\nKeyError: 'id'";
+        assert_eq!(
+            parse_frames(python),
+            vec![
+                frame("handler", "app/handlers.py"),
+                frame("<module>", "app/main.py"),
+            ]
+        );
+
+        let java = "java.lang.IllegalStateException: closed\n\tat java.base/java.io.Reader.read(Native Method)\n\t... 12 more\nCaused by: boom\nat x.Y.z(Y.java:1)";
+        assert_eq!(
+            parse_frames(java),
+            vec![
+                frame("java.base/java.io.Reader.read", "Native Method"),
+                frame("x.Y.z", "Y.java"),
+            ]
+        );
+        assert!(parse_frames("timeout at 10:30:in queue").is_empty());
+    }
+
+    #[test]
+    fn frames_decide_the_distance() {
+        let metric = stack_trace_metric(FrameParser::new());
+        let a = "NullPointerException: a is null\n\tat com.shop.Cart.total(Cart.java:42)\n\tat com.shop.Checkout.run(Checkout.java:17)";
+        let b = "NullPointerException: b is null\n\tat com.shop.Cart.total(Cart.java:44)\n\tat com.shop.Checkout.run(Checkout.java:18)";
+        let outer_only = "IllegalStateException: closed\n\tat com.shop.Stock.reserve(Stock.java:9)\n\tat com.shop.Checkout.run(Checkout.java:17)";
+
+        assert_eq!(metric(a, b), Distance::MIN);
+        // Sharing the outer frame alone: 1/2 of 1 + 1/2 + 1.
+        let d = metric(a, outer_only).value();
+        assert!((d - (1.0 - 0.5 / 2.5)).abs() < 1e-6);
+        assert_eq!(metric(a, "timeout talking to db"), Distance::MAX);
+        assert!(metric("timeout talking to db", "timeout talking to db!").value() < 0.1);
+    }
+
+    #[test]
+    fn default_config_groups_traces_whatever_their_messages() {
+        let frames = "\n\tat com.shop.Cart.total(Cart.java:42)\n\tat com.shop.Checkout.run(Checkout.java:17)";
+        let a = format!(
+            "IllegalStateException: checkout expired before payment{}",
+            frames
+        );
+        let b = format!(
+            "NullPointerException: price of item 8812 in cart 19 is null{}",
+            frames
+        );
+        let other = "IllegalStateException: closed\n\tat com.shop.Stock.reserve(Stock.java:9)";
+        let records = vec![a.clone(), b.clone(), other.to_string()];
+        let threshold = 0.25_f64.try_into().expect("valid threshold");
+
+        let groups = crate::group_similar(&records, &crate::Config::stack_trace(threshold));
+        assert_eq!(groups.len(), 2);
+        let (representative, members) = groups
+            .iter()
+            .find(|(_, members)| !members.is_empty())
+            .expect("a and b grouped");
+        let mut grouped = vec![*representative, members[0]];
+        grouped.sort();
+        assert_eq!(grouped, vec![&a, &b]);
+    }
+
+    #[test]
+    fn cleanup_ignores_generated_names() {
+        let metric = stack_trace_metric(FrameParser::new());
+        let a = "boom\n\tat com.shop.Jobs.lambda$run$12(Jobs.java:5)";
+        let b = "boom\n\tat com.shop.Jobs.lambda$run$7(Jobs.java:9)";
+        assert_eq!(metric(a, b), Distance::MIN);
+    }
+}
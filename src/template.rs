@@ -0,0 +1,166 @@
+//! Drain-style templates summarizing a cluster's members.
+//!
+//! Each member is split into tokens with [`crate::tokens::tokenize`] and
+//! aligned against the first member by longest common subsequence. Tokens
+//! shared by every member are kept; positions where members disagree, and
+//! places where some member has extra tokens, become `<*>`. The template
+//! keeps the first member's punctuation and spacing, so it reads like one of
+//! the records.
+
+use crate::tokens::tokenize;
+use std::collections::HashSet;
+
+/// Wildcard standing for a varying token or run of tokens.
+pub const WILDCARD: &str = "<*>";
+
+/// The common token skeleton of `members`, with [`WILDCARD`] in varying
+/// positions.
+///
+/// Duplicates don't change the result. Members are usually normalized keys,
+/// so values the normalizer already replaced stay as their placeholder.
+///
+/// ```
+/// use group_similar::cluster_template;
+///
+/// let template = cluster_template(&[
+///     "undefined method 'name' for #<Shop::Product:0x_ @id=12>",
+///     "undefined method 'price' for #<Shop::Product:0x_ @id=7>",
+/// ]);
+/// assert_eq!(template, "undefined method '<*>' for #<Shop::Product:0x_ @id=<*>>");
+/// ```
+pub fn cluster_template<S: AsRef<str>>(members: &[S]) -> String {
+    let first = match members.first() {
+        Some(first) => first.as_ref(),
+        None => return String::new(),
+    };
+    let skeleton: Vec<(usize, &str)> = spans(first).collect();
+    let mut wild = vec![false; skeleton.len()];
+    // `gaps[k]`: some member has tokens the skeleton lacks just before
+    // skeleton token `k` (or at the end, for `k == skeleton.len()`).
+    let mut gaps = vec![false; skeleton.len() + 1];
+
+    let mut seen: HashSet<&str> = HashSet::new();
+    seen.insert(first);
+    for member in members[1..].iter().map(AsRef::as_ref) {
+        if !seen.insert(member) {
+            continue;
+        }
+
+        let tokens: Vec<&str> = tokenize(member).collect();
+        let (mut prev_s, mut prev_t) = (0, 0);
+        let matched = lcs(&skeleton, &tokens);
+        for &(s, t) in matched.iter().chain(&[(skeleton.len(), tokens.len())]) {
+            wild[prev_s..s].iter_mut().for_each(|w| *w = true);
+            if s == prev_s && t > prev_t {
+                gaps[s] = true;
+            }
+            prev_s = s + 1;
+            prev_t = t + 1;
+        }
+    }
+
+    render(first, &skeleton, &wild, &gaps)
+}
+
+/// Tokens of `s` with their byte offsets.
+fn spans(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    let base = s.as_ptr() as usize;
+    tokenize(s).map(move |t| (t.as_ptr() as usize - base, t))
+}
+
+/// Index pairs `(skeleton, tokens)` of a longest common subsequence, in
+/// order.
+fn lcs(skeleton: &[(usize, &str)], tokens: &[&str]) -> Vec<(usize, usize)> {
+    let (n, m) = (skeleton.len(), tokens.len());
+    // `table[i][j]`: LCS length of `skeleton[i..]` and `tokens[j..]`.
+    let mut table = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i][j] = if skeleton[i].1 == tokens[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(table[0][0] as usize);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if skeleton[i].1 == tokens[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn render(first: &str, skeleton: &[(usize, &str)], wild: &[bool], gaps: &[bool]) -> String {
+    let mut out = String::with_capacity(first.len());
+    let mut last = 0;
+    for (k, &(start, token)) in skeleton.iter().enumerate() {
+        let separator = &first[last..start];
+        out.push_str(separator);
+        if gaps[k] && !wild[k] && !out.ends_with(WILDCARD) {
+            out.push_str(WILDCARD);
+            // Keep the wildcard apart from the next token.
+            if separator.is_empty() || separator.contains(char::is_whitespace) {
+                out.push(' ');
+            } else {
+                out.push_str(separator);
+            }
+        }
+        if !wild[k] {
+            out.push_str(token);
+        } else if !out.ends_with(WILDCARD) {
+            out.push_str(WILDCARD);
+        }
+        last = start + token.len();
+    }
+    out.push_str(&first[last..]);
+    if gaps[skeleton.len()] && !out.ends_with(WILDCARD) {
+        if last == first.len() && !first.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(WILDCARD);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varying_tokens_become_wildcards() {
+        let members = vec![
+            "user 12 logged in from web",
+            "user 7 logged in from mobile",
+            "user 7 logged in from mobile",
+        ];
+        assert_eq!(cluster_template(&members), "user <*> logged in from <*>");
+    }
+
+    #[test]
+    fn extra_tokens_become_a_single_wildcard() {
+        let members = vec![
+            "payment failed: card declined",
+            "payment failed: card expired last month",
+            "payment failed: insufficient funds on card declined",
+        ];
+        assert_eq!(cluster_template(&members), "payment failed: <*> card <*>");
+        assert_eq!(cluster_template(&["a b", "a b c"]), "a b <*>");
+        assert_eq!(cluster_template(&["a b", "x a b"]), "<*> a b");
+    }
+
+    #[test]
+    fn single_member_is_its_own_template() {
+        assert_eq!(cluster_template(&["as is, (kept)"]), "as is, (kept)");
+        assert_eq!(cluster_template::<&str>(&[]), "");
+    }
+}
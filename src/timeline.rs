@@ -0,0 +1,270 @@
+//! When a cluster's records happened.
+//!
+//! [`find_timestamp`] pulls the first ISO-8601 timestamp out of a record —
+//! leading, as in most log formats, or embedded — using the same patterns
+//! [`crate::normalize`] replaces. [`Timeline`] then summarizes a cluster's
+//! members as first-seen, last-seen and per-bucket counts, so a family of
+//! errors that only started after a deploy stands out.
+
+use crate::normalize::{capture_patterns, Placeholders};
+
+/// First- and last-seen times and bucketed counts for a set of records.
+/// Times are Unix seconds (UTC).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    /// Earliest timestamp, if any record had one.
+    pub first_seen: Option<i64>,
+    /// Latest timestamp, if any record had one.
+    pub last_seen: Option<i64>,
+    /// Records with no timestamp.
+    pub undated: usize,
+    /// `(bucket start, count)` for every bucket holding a record, in time
+    /// order. Buckets are aligned to multiples of their width since the
+    /// epoch.
+    pub buckets: Vec<(i64, usize)>,
+}
+
+impl Timeline {
+    /// Summarize `records`, reading each one's time with `timestamp` (usually
+    /// [`find_timestamp`]) and counting them in buckets `bucket` seconds
+    /// wide.
+    ///
+    /// ```
+    /// use group_similar::{find_timestamp, Timeline};
+    ///
+    /// let records = [
+    ///     "2026-03-25T03:56:10Z timeout talking to db",
+    ///     "2026-03-25T03:59:00Z timeout talking to db",
+    ///     "2026-03-25T05:10:00Z timeout talking to db",
+    ///     "timeout talking to db",
+    /// ];
+    /// let timeline = Timeline::new(&records, |r| find_timestamp(r), 3600);
+    /// assert_eq!(timeline.undated, 1);
+    /// assert_eq!(timeline.buckets.len(), 2);
+    /// assert_eq!(timeline.buckets[0].1, 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `bucket` is not positive.
+    pub fn new<V, F>(records: &[V], timestamp: F, bucket: i64) -> Self
+    where
+        F: Fn(&V) -> Option<i64>,
+    {
+        assert!(bucket > 0, "bucket width must be positive");
+        let mut times: Vec<i64> = Vec::with_capacity(records.len());
+        let mut undated = 0;
+        for record in records {
+            match timestamp(record) {
+                Some(t) => times.push(t),
+                None => undated += 1,
+            }
+        }
+        times.sort_unstable();
+
+        let mut buckets: Vec<(i64, usize)> = Vec::new();
+        for &t in &times {
+            let start = t.div_euclid(bucket) * bucket;
+            match buckets.last_mut() {
+                Some((last, count)) if *last == start => *count += 1,
+                _ => buckets.push((start, 1)),
+            }
+        }
+
+        Self {
+            first_seen: times.first().copied(),
+            last_seen: times.last().copied(),
+            undated,
+            buckets,
+        }
+    }
+}
+
+/// The first ISO-8601 timestamp in `record` that [`parse_timestamp`]
+/// accepts, quoted or not, as Unix seconds.
+pub fn find_timestamp(record: &str) -> Option<i64> {
+    capture_patterns(record, Placeholders::Fixed)
+        .1
+        .into_iter()
+        .filter(|c| matches!(c.pattern, "timestamps" | "iso_timestamps"))
+        .find_map(|c| parse_timestamp(record[c.span].trim_matches('"')))
+}
+
+/// Parse an ISO-8601 date or date-time as Unix seconds: `2026-03-25`,
+/// `2026-03-25T03:56:10.370Z`, `2026-03-25 03:56:10 +0000`,
+/// `2026-03-25T03:56+02:00`. Fractional seconds are dropped; without a zone
+/// the time is taken as UTC.
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let b = s.as_bytes();
+    let year = number(b, 0, 4)?;
+    let month = number(b, 5, 2)?;
+    let day = number(b, 8, 2)?;
+    if b.get(4) != Some(&b'-') || b.get(7) != Some(&b'-') {
+        return None;
+    }
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86_400;
+
+    let mut i = 10;
+    if matches!(b.get(i), Some(b'T') | Some(b' ')) && b.get(i + 3) == Some(&b':') {
+        let hour = number(b, i + 1, 2)?;
+        let minute = number(b, i + 4, 2)?;
+        i += 6;
+        let mut second = 0;
+        if b.get(i) == Some(&b':') {
+            second = number(b, i + 1, 2)?;
+            i += 3;
+            if matches!(b.get(i), Some(b'.') | Some(b',')) {
+                i += 1;
+                while b.get(i).is_some_and(u8::is_ascii_digit) {
+                    i += 1;
+                }
+            }
+        }
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        seconds += hour * 3600 + minute * 60 + second;
+
+        if b.get(i) == Some(&b' ') && matches!(b.get(i + 1), Some(b'+') | Some(b'-')) {
+            i += 1;
+        }
+        match b.get(i) {
+            Some(b'Z') => i += 1,
+            Some(&sign @ (b'+' | b'-')) => {
+                let hours = number(b, i + 1, 2)?;
+                i += 3;
+                if b.get(i) == Some(&b':') {
+                    i += 1;
+                }
+                let minutes = number(b, i, 2).unwrap_or(0);
+                if b.get(i).is_some_and(u8::is_ascii_digit) {
+                    i += 2;
+                }
+                let offset = hours * 3600 + minutes * 60;
+                seconds -= if sign == b'+' { offset } else { -offset };
+            }
+            _ => {}
+        }
+    }
+
+    if i == b.len() {
+        Some(seconds)
+    } else {
+        None
+    }
+}
+
+/// Format Unix seconds as `YYYY-MM-DD HH:MM:SS` (UTC).
+pub fn format_timestamp(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// `len` ASCII digits at `at` as a number.
+fn number(b: &[u8], at: usize, len: usize) -> Option<i64> {
+    let digits = b.get(at..at + len)?;
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    Some(digits.iter().fold(0, |n, &d| n * 10 + i64::from(d - b'0')))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversions between proleptic Gregorian dates and days since 1970-01-01,
+// after Howard Hinnant's `days_from_civil` / `civil_from_days`.
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn parses_iso_variants() {
+        assert_eq!(parse_timestamp("1970-01-01"), Some(0));
+        assert_eq!(parse_timestamp("2026-03-25T03:56:10Z"), Some(1_774_410_970));
+        assert_eq!(
+            parse_timestamp("2026-03-25 03:56:10.370950000 +0000"),
+            Some(1_774_410_970)
+        );
+        assert_eq!(
+            parse_timestamp("2026-03-25T05:56:10+02:00"),
+            Some(1_774_410_970)
+        );
+        assert_eq!(parse_timestamp("2026-03-25T03:56"), Some(1_774_410_960));
+        assert_eq!(parse_timestamp("2026-02-30"), None);
+        assert_eq!(parse_timestamp("2026-03-25 and more"), None);
+    }
+
+    #[test]
+    fn finds_leading_and_embedded_timestamps() {
+        assert_eq!(
+            find_timestamp("2026-03-25T03:56:10Z ERROR boom"),
+            Some(1_774_410_970)
+        );
+        assert_eq!(
+            find_timestamp("boom id: 7, created_at: \"2026-03-25 03:56:10 +0000\""),
+            Some(1_774_410_970)
+        );
+        assert_eq!(find_timestamp("boom id: 7"), None);
+    }
+
+    #[test]
+    fn timeline_buckets_records() {
+        let times = [7200, 10, 3599, 3600, -1];
+        let timeline = Timeline::new(&times, |&t| if t < 0 { None } else { Some(t) }, 3600);
+        assert_eq!(timeline.first_seen, Some(10));
+        assert_eq!(timeline.last_seen, Some(7200));
+        assert_eq!(timeline.undated, 1);
+        assert_eq!(timeline.buckets, vec![(0, 2), (3600, 1), (7200, 1)]);
+    }
+
+    #[quickcheck]
+    fn prop_format_then_parse_round_trips(seconds: i32) -> bool {
+        let seconds = i64::from(seconds).abs();
+        parse_timestamp(&format_timestamp(seconds)) == Some(seconds)
+    }
+}
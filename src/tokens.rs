@@ -25,6 +25,55 @@ pub(crate) fn tokenize(s: &str) -> impl Iterator<Item = &str> {
 /// `ln(N / df(t))` (using `ln_1p` to keep the result strictly positive when
 /// a token appears in every record).
 pub(crate) fn build_idf<V: AsRef<str>>(corpus: &[V]) -> (HashMap<String, u32>, Vec<f32>) {
+    let (vocab, df) = document_frequencies(corpus);
+
+    let n = corpus.len() as f32;
+    // ln_1p(x) = ln(1 + x) — keeps result strictly positive when df = N.
+    let idf: Vec<f32> = df.iter().map(|c| (n / (*c as f32)).ln_1p()).collect();
+
+    (vocab, idf)
+}
+
+/// Like [`build_idf`], with each record counting as `weight(record)`
+/// documents: `N` is the total weight and `df(t)` the total weight of the
+/// records containing `t`. Pre-aggregated input (one line standing for many
+/// occurrences) then gets the IDF of its expanded form.
+pub(crate) fn build_weighted_idf<V, W>(corpus: &[V], weight: W) -> (HashMap<String, u32>, Vec<f32>)
+where
+    V: AsRef<str>,
+    W: Fn(&V) -> f32,
+{
+    let (vocab, counts) = document_frequencies(corpus);
+    let mut df = vec![0.0f32; counts.len()];
+    let mut total = 0.0f32;
+    let mut seen_ids: Vec<u32> = Vec::new();
+
+    for record in corpus {
+        let w = weight(record);
+        total += w;
+        seen_ids.clear();
+        for tok in tokenize(record.as_ref()) {
+            let id = vocab[tok];
+            if !seen_ids.contains(&id) {
+                seen_ids.push(id);
+                df[id as usize] += w;
+            }
+        }
+    }
+
+    let idf = df
+        .iter()
+        .map(|&d| if d > 0.0 { (total / d).ln_1p() } else { 0.0 })
+        .collect();
+    (vocab, idf)
+}
+
+/// Scan the corpus and build a vocabulary plus per-token document counts.
+///
+/// `df[id]` is the number of records containing the token at least once.
+pub(crate) fn document_frequencies<V: AsRef<str>>(
+    corpus: &[V],
+) -> (HashMap<String, u32>, Vec<u32>) {
     let mut vocab: HashMap<String, u32> = HashMap::new();
     let mut df: Vec<u32> = Vec::new();
     let mut next_id: u32 = 0;
@@ -50,11 +99,7 @@ pub(crate) fn build_idf<V: AsRef<str>>(corpus: &[V]) -> (HashMap<String, u32>, V
         }
     }
 
-    let n = corpus.len() as f32;
-    // ln_1p(x) = ln(1 + x) — keeps result strictly positive when df = N.
-    let idf: Vec<f32> = df.iter().map(|c| (n / (*c as f32)).ln_1p()).collect();
-
-    (vocab, idf)
+    (vocab, df)
 }
 
 /// Vectorize a single string against a precomputed vocab and idf.
@@ -153,6 +198,22 @@ mod tests {
         assert!(gamma_idf > shared_idf);
     }
 
+    #[test]
+    fn weighted_idf_counts_each_record_by_weight() {
+        let corpus = [("alpha shared", 1.0), ("beta shared", 1.0), ("alpha", 6.0)];
+        let texts: Vec<&str> = corpus.iter().map(|(t, _)| *t).collect();
+        let (vocab, idf) =
+            build_weighted_idf(&texts, |t| corpus.iter().find(|(c, _)| c == t).unwrap().1);
+        // "alpha" covers 7 of 8 weighted documents, "shared" only 2.
+        let alpha = idf[vocab["alpha"] as usize];
+        let shared = idf[vocab["shared"] as usize];
+        assert!(alpha < shared);
+        assert!((alpha - (8.0f32 / 7.0).ln_1p()).abs() < 1e-6);
+
+        let (_, unweighted) = build_weighted_idf(&texts, |_| 1.0);
+        assert_eq!(unweighted, build_idf(&texts).1);
+    }
+
     #[test]
     fn cosine_same_entity_high_different_low() {
         // Three records of one entity sharing leading tokens, two records of
@@ -0,0 +1,136 @@
+//! Records standing for more than one occurrence.
+//!
+//! Input is often pre-aggregated — `uniq -c` output, a `GROUP BY` export —
+//! so one line represents many identical records. [`Weighted`] exposes that
+//! count. [`group_similar_weighted`] keys each cluster by its heaviest member,
+//! and [`Config::token_cosine_weighted`](crate::Config::token_cosine_weighted)
+//! fits IDF as if every record were repeated `weight` times.
+
+use crate::{group_similar, Config};
+use std::collections::BTreeMap;
+
+/// A record's multiplicity: how many occurrences it stands for.
+pub trait Weighted {
+    /// Number of occurrences; `0` is allowed and contributes nothing.
+    fn weight(&self) -> u64;
+}
+
+/// A value paired with its occurrence count, as read from pre-aggregated
+/// input.
+///
+/// Compares by value first, so identical values with different counts stay
+/// distinct records.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Counted<V> {
+    /// The record.
+    pub value: V,
+    /// How many times it occurred.
+    pub count: u64,
+}
+
+impl<V> Counted<V> {
+    /// Pair `value` with `count`.
+    pub fn new(value: V, count: u64) -> Self {
+        Self { value, count }
+    }
+}
+
+impl<V: AsRef<str>> AsRef<str> for Counted<V> {
+    fn as_ref(&self) -> &str {
+        self.value.as_ref()
+    }
+}
+
+impl<V> Weighted for Counted<V> {
+    fn weight(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Total weight of `records`.
+pub fn total_weight<'a, V, I>(records: I) -> u64
+where
+    V: Weighted + 'a,
+    I: IntoIterator<Item = &'a V>,
+{
+    records.into_iter().map(Weighted::weight).sum()
+}
+
+/// [`group_similar`] for weighted records: the same clusters, each keyed by
+/// its heaviest member (the smallest one on ties) rather than its first.
+///
+/// The remaining members keep their order. A cluster's size is
+/// [`total_weight`] of its key and members.
+///
+/// Weights only pick the key and add up to sizes: clustering itself treats
+/// every record as one, so a record standing for 1,000 occurrences pulls on
+/// linkage no harder than one standing for a single occurrence.
+///
+/// ```
+/// use group_similar::{group_similar_weighted, total_weight, Config, Counted};
+/// use std::convert::TryInto;
+///
+/// let records = vec![
+///     Counted::new("timeout talking to db", 3),
+///     Counted::new("timeout talking to db!", 40),
+///     Counted::new("disk full on /var", 2),
+/// ];
+/// let config = Config::jaro_winkler(0.1_f64.try_into().unwrap());
+/// let results = group_similar_weighted(&records, &config);
+///
+/// let (key, members) = results.iter().find(|(k, _)| k.count == 40).unwrap();
+/// assert_eq!(key.value, "timeout talking to db!");
+/// assert_eq!(total_weight(std::iter::once(*key).chain(members.iter().copied())), 43);
+/// ```
+pub fn group_similar_weighted<'a, V>(
+    records: &'a [V],
+    config: &Config<V>,
+) -> BTreeMap<&'a V, Vec<&'a V>>
+where
+    V: std::hash::Hash + AsRef<str> + Eq + Sync + Ord + Weighted,
+{
+    group_similar(records, config)
+        .into_iter()
+        .map(|(key, members)| {
+            let mut all = Vec::with_capacity(members.len() + 1);
+            all.push(key);
+            all.extend(members);
+            let heaviest = all.iter().enumerate().fold(0, |best, (i, v)| {
+                let (w, b) = (v.weight(), all[best].weight());
+                if w > b || (w == b && v < &all[best]) {
+                    i
+                } else {
+                    best
+                }
+            });
+            let key = all.remove(heaviest);
+            (key, all)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn heaviest_member_keys_the_cluster() {
+        let records = vec![
+            Counted::new("user 1 logged in", 2),
+            Counted::new("user 2 logged in", 9),
+            Counted::new("user 3 logged in", 9),
+            Counted::new("payment declined", 1),
+        ];
+        let config = Config::jaro_winkler(0.2_f64.try_into().unwrap());
+        let results = group_similar_weighted(&records, &config);
+
+        assert_eq!(results.len(), 2);
+        // "user 2" and "user 3" tie; the smaller one keys the cluster.
+        let members = &results[&records[1]];
+        assert_eq!(members.len(), 2);
+        assert!(members.contains(&&records[0]) && members.contains(&&records[2]));
+        assert_eq!(total_weight(members.iter().copied()), 11);
+        assert!(results[&records[3]].is_empty());
+    }
+}